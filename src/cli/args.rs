@@ -0,0 +1,215 @@
+use airbnb_reservations_scraper::helpers::dates::{normalize_date, DateBound};
+use airbnb_reservations_scraper::models::query::ReservationStatus;
+use airbnb_reservations_scraper::Error;
+use chrono::Local;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Airbnb hosting reservations scraper")]
+pub struct Cli {
+    /// Env-style file with configuration keys (loaded before `.env`)
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Directory where the generated files are written
+    #[arg(long, global = true, default_value = ".")]
+    pub output_dir: PathBuf,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Download reservations and write the extraction workbook
+    Fetch(FetchArgs),
+    /// Parse earnings and join the reservations with the base workbook
+    Process(ProcessArgs),
+    /// Write the consolidated workbook from a processed file
+    Report(ReportArgs),
+    /// Run fetch, process and report in sequence
+    Run(FetchArgs),
+    /// Write one statement workbook per owner from a processed file
+    Statements(StatementsArgs),
+    /// Fetch only what changed since the last sync into the local store
+    Sync(SyncArgs),
+    /// Compare two reservations snapshots written by `fetch`
+    Diff(DiffArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct FetchArgs {
+    /// Min search date: YYYY-MM-DD, DD/MM/YYYY, -30d, last-month, this-year, ...
+    /// (defaults to the recorded query with --from-raw)
+    #[arg(
+        long,
+        value_parser = parse_date_min,
+        required_unless_present = "replay",
+        allow_hyphen_values = true
+    )]
+    pub date_min: Option<String>,
+
+    /// Max search date, same formats (last-month is its last day)
+    #[arg(long, value_parser = parse_date_max, allow_hyphen_values = true)]
+    pub date_max: Option<String>,
+
+    #[command(flatten)]
+    pub filters: QueryArgs,
+
+    /// Read saved API pages from this directory, e.g. a raw run directory,
+    /// instead of calling Airbnb
+    #[arg(long, visible_alias = "from-raw")]
+    pub replay: Option<PathBuf>,
+
+    /// Previous reservations JSON to diff the fetched reservations against
+    #[arg(long)]
+    pub diff_against: Option<PathBuf>,
+}
+
+pub fn parse_date_min(value: &str) -> Result<String, String> {
+    normalize_date(value, DateBound::Start, Local::now().date_naive()).map_err(|e| e.to_string())
+}
+
+fn parse_date_max(value: &str) -> Result<String, String> {
+    normalize_date(value, DateBound::End, Local::now().date_naive()).map_err(|e| e.to_string())
+}
+
+fn parse_status(value: &str) -> Result<ReservationStatus, String> {
+    value.parse().map_err(|e: Error| e.to_string())
+}
+
+/// Overrides of the `QUERY_*` config keys
+#[derive(Debug, Default, Args)]
+pub struct QueryArgs {
+    /// Reservation statuses: accepted, request, pending, cancelled, past
+    #[arg(long = "status", value_delimiter = ',', value_parser = parse_status)]
+    pub statuses: Vec<ReservationStatus>,
+
+    /// Only reservations of these listing IDs
+    #[arg(long = "listing-id", value_delimiter = ',')]
+    pub listing_ids: Vec<String>,
+
+    /// Currency of the earnings
+    #[arg(long)]
+    pub currency: Option<String>,
+
+    /// Locale of the localized fields
+    #[arg(long)]
+    pub locale: Option<String>,
+
+    /// Reservations per page
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub page_size: Option<u32>,
+}
+
+#[derive(Debug, Args)]
+pub struct SyncArgs {
+    #[command(flatten)]
+    pub fetch: FetchArgs,
+
+    /// JSON-lines store file (defaults to STORE_PATH)
+    #[arg(long)]
+    pub store: Option<PathBuf>,
+
+    /// Ignore previous syncs and refetch everything from --date-min
+    #[arg(long)]
+    pub full: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// Older reservations JSON
+    #[arg(long)]
+    pub previous: PathBuf,
+
+    /// Newer reservations JSON
+    #[arg(long)]
+    pub current: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ProcessArgs {
+    /// Reservations JSON written by `fetch`
+    #[arg(long)]
+    pub input: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// Consolidated JSON written by `process`
+    #[arg(long)]
+    pub input: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct StatementsArgs {
+    /// Consolidated JSON written by `process`
+    #[arg(long)]
+    pub input: PathBuf,
+
+    /// First day of the statement period, same formats as fetch (defaults to
+    /// each owner's first check-in)
+    #[arg(
+        long,
+        value_parser = parse_date_min,
+        requires = "date_max",
+        allow_hyphen_values = true
+    )]
+    pub date_min: Option<String>,
+
+    /// Last day of the statement period (defaults to each owner's last
+    /// checkout)
+    #[arg(
+        long,
+        value_parser = parse_date_max,
+        requires = "date_min",
+        allow_hyphen_values = true
+    )]
+    pub date_max: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_relative_dates_given_as_separate_values() {
+        let cli = Cli::try_parse_from([
+            "scraper",
+            "fetch",
+            "--date-min",
+            "-30d",
+            "--date-max",
+            "-2w",
+        ])
+        .unwrap();
+        let Command::Fetch(args) = cli.command else {
+            panic!("expected the fetch command");
+        };
+        assert_eq!(args.date_min, Some(parse_date_min("-30d").unwrap()));
+        assert_eq!(args.date_max, Some(parse_date_max("-2w").unwrap()));
+
+        let cli = Cli::try_parse_from([
+            "scraper",
+            "statements",
+            "--input",
+            "consolidated.json",
+            "--date-min",
+            "-3m",
+            "--date-max",
+            "-1m",
+        ])
+        .unwrap();
+        let Command::Statements(args) = cli.command else {
+            panic!("expected the statements command");
+        };
+        assert_eq!(args.date_min, Some(parse_date_min("-3m").unwrap()));
+        assert_eq!(args.date_max, Some(parse_date_max("-1m").unwrap()));
+    }
+
+    #[test]
+    fn rejects_invalid_relative_dates() {
+        assert!(Cli::try_parse_from(["scraper", "fetch", "--date-min", "-30x"]).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+use crate::cli::args::{
+    parse_date_min, Cli, Command, FetchArgs, QueryArgs, StatementsArgs, SyncArgs,
+};
+use airbnb_reservations_scraper::helpers::dates::validate_range;
+use airbnb_reservations_scraper::helpers::diff::ReservationDiff;
+use airbnb_reservations_scraper::helpers::engine::restore_dates;
+use airbnb_reservations_scraper::models::api::SchemaReport;
+use airbnb_reservations_scraper::models::query::ReservationQuery;
+use airbnb_reservations_scraper::pipeline;
+use airbnb_reservations_scraper::sources::recording::MANIFEST_FILE;
+use airbnb_reservations_scraper::sources::ReservationSource;
+use airbnb_reservations_scraper::store::Store;
+use airbnb_reservations_scraper::{Config, Error, Reservation, Result};
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::fs::{self, File};
+use std::io::{stdin, stdout, Read, Write};
+use std::path::{Path, PathBuf};
+
+fn create_file(path: &Path) -> Result<File> {
+    File::create(path).map_err(|e| Error::io(format!("Could not create {}", path.display()), e))
+}
+
+fn open_file(path: &Path) -> Result<File> {
+    File::open(path).map_err(|e| Error::io(format!("Could not open {}", path.display()), e))
+}
+
+fn read_reservations(path: &Path) -> Result<Vec<Reservation>> {
+    serde_json::from_reader(open_file(path)?)
+        .map_err(|e| Error::parse(path.display().to_string(), e))
+}
+
+/// Consolidated JSON written by `process`, with its date columns restored.
+fn read_consolidated(path: &Path) -> Result<DataFrame> {
+    let cons_df = JsonReader::new(open_file(path)?)
+        .finish()
+        .map_err(|e| Error::parse(path.display().to_string(), e))?;
+    restore_dates(cons_df)
+}
+
+pub fn execute(cli: Cli) -> Result<()> {
+    if let Some(config_file) = &cli.config {
+        dotenv::from_path(config_file).map_err(|e| {
+            Error::Config(format!("could not load {}: {}", config_file.display(), e))
+        })?;
+    }
+    let config = Config::from_env()?;
+    fs::create_dir_all(&cli.output_dir)
+        .map_err(|e| Error::io("Could not create the output directory", e))?;
+
+    match cli.command {
+        Command::Fetch(args) => {
+            let data = fetch(&config, &args, &cli.output_dir)?;
+            let filepath =
+                config.output_path(&cli.output_dir, &config.output_file_prefix_1, "json");
+            serde_json::to_writer_pretty(create_file(&filepath)?, &data)
+                .map_err(|e| Error::io("Could not write reservations", e.into()))?;
+            println!("Reservations have been saved in {}", filepath.display());
+        }
+        Command::Process(args) => {
+            let data = read_reservations(&args.input)?;
+            let mut cons_df = process(&config, data)?;
+            let filepath =
+                config.output_path(&cli.output_dir, &config.output_file_prefix_2, "json");
+            JsonWriter::new(&mut create_file(&filepath)?)
+                .with_json_format(JsonFormat::Json)
+                .finish(&mut cons_df)?;
+            println!("Consolidated data has been saved in {}", filepath.display());
+        }
+        Command::Report(args) => {
+            let cons_df = read_consolidated(&args.input)?;
+            report(&config, cons_df, None, &cli.output_dir)?;
+        }
+        Command::Run(args) => {
+            let period = query(&config, &args)?.period();
+            let data = fetch(&config, &args, &cli.output_dir)?;
+            report(&config, process(&config, data)?, period, &cli.output_dir)?;
+        }
+        Command::Statements(args) => {
+            let cons_df = read_consolidated(&args.input)?;
+            statements(&config, &args, &cons_df, &cli.output_dir)?;
+        }
+        Command::Sync(args) => {
+            let data = sync(&config, &args, &cli.output_dir)?;
+            let filepath =
+                config.output_path(&cli.output_dir, &config.output_file_prefix_1, "json");
+            serde_json::to_writer_pretty(create_file(&filepath)?, &data)
+                .map_err(|e| Error::io("Could not write reservations", e.into()))?;
+            println!(
+                "Stored reservations have been saved in {}",
+                filepath.display()
+            );
+        }
+        Command::Diff(args) => {
+            let previous = read_reservations(&args.previous)?;
+            let current = read_reservations(&args.current)?;
+            write_diff(
+                &config,
+                &pipeline::diff(&previous, &current),
+                &cli.output_dir,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Prompt-driven flow used when the executable is launched without arguments.
+pub fn interactive() -> Result<()> {
+    // Ask again until the date parses, nothing is fetched before that
+    let min_date = loop {
+        let mut input = String::new();
+        print!("Enter a min search date: ");
+        let _ = stdout().flush();
+        let read = stdin().read_line(&mut input).unwrap_or(0);
+        match parse_date_min(&input) {
+            Ok(date) => break date,
+            Err(_) if read == 0 => {
+                return Err(Error::parse(
+                    "date",
+                    "stdin closed before a date was entered",
+                ))
+            }
+            Err(message) => println!("{}", message),
+        }
+    };
+    println!("{}", min_date);
+
+    let args = FetchArgs {
+        date_min: Some(min_date),
+        date_max: None,
+        filters: QueryArgs::default(),
+        replay: None,
+        diff_against: None,
+    };
+    let output_dir = PathBuf::from(".");
+    let result = Config::from_env().and_then(|config| {
+        let data = fetch(&config, &args, &output_dir)?;
+        report(&config, process(&config, data)?, None, &output_dir)
+    });
+    // Keep the window open long enough to read the error as well
+    if let Err(error) = &result {
+        println!("Error: {}", error);
+    }
+
+    println!("Press 'ENTER' to close...");
+    let _ = stdout().flush();
+    // A closed or piped stdin just ends the prompt
+    let _ = stdin().read(&mut [0]);
+    result
+}
+
+/// Config defaults, or the recorded query of a raw run, overridden by the CLI
+/// flags and checked before any request.
+fn query(config: &Config, args: &FetchArgs) -> Result<ReservationQuery> {
+    let recorded = match &args.replay {
+        Some(dir) if dir.join(MANIFEST_FILE).exists() => Some(pipeline::recorded_query(dir)?),
+        _ => None,
+    };
+    let mut query = match (&args.date_min, recorded) {
+        (Some(date_min), Some(recorded)) => ReservationQuery {
+            date_min: date_min.clone(),
+            ..recorded
+        },
+        (Some(date_min), None) => config.query(date_min),
+        (None, Some(recorded)) => recorded,
+        (None, None) => {
+            return Err(Error::Config(
+                "--date-min is required unless the replay directory has a manifest".to_string(),
+            ))
+        }
+    };
+    if args.date_max.is_some() {
+        query.date_max = args.date_max.clone();
+    }
+    let filters = &args.filters;
+    if !filters.statuses.is_empty() {
+        query.statuses = filters.statuses.clone();
+    }
+    if !filters.listing_ids.is_empty() {
+        query.listing_ids = filters.listing_ids.clone();
+    }
+    if let Some(currency) = &filters.currency {
+        query.currency = currency.clone();
+    }
+    if let Some(locale) = &filters.locale {
+        query.locale = locale.clone();
+    }
+    if let Some(page_size) = filters.page_size {
+        query.page_size = page_size;
+    }
+    validate_range(&query.date_min, query.date_max.as_deref())?;
+    Ok(query)
+}
+
+/// The replay directory, or the live API with every page saved in a raw run
+/// directory.
+fn open_source(
+    config: &Config,
+    args: &FetchArgs,
+    output_dir: &Path,
+) -> Result<Box<dyn ReservationSource>> {
+    let source = pipeline::open_source(config, args.replay.as_deref())?;
+    if args.replay.is_some() {
+        return Ok(source);
+    }
+    let source = pipeline::record(config, source, output_dir)?;
+    println!("Raw pages are saved in {}", source.dir().display());
+    Ok(Box::new(source))
+}
+
+fn fetch(config: &Config, args: &FetchArgs, output_dir: &Path) -> Result<Vec<Reservation>> {
+    println!("Starting extraction step...");
+    let query = query(config, args)?;
+    let mut source = open_source(config, args, output_dir)?;
+    let (data, schema) = pipeline::fetch(source.as_mut(), &query)?;
+    report_schema(config, &schema, output_dir)?;
+    let output_filepath = pipeline::export_reservations(config, &data, output_dir)?;
+    println!(
+        "Data has been extracted and written in {}",
+        output_filepath.display()
+    );
+    if let Some(previous) = &args.diff_against {
+        let diff = pipeline::diff(&read_reservations(previous)?, &data);
+        write_diff(config, &diff, output_dir)?;
+    }
+    Ok(data)
+}
+
+/// Prints and saves the fields Airbnb added or stopped sending, if any.
+fn report_schema(config: &Config, schema: &SchemaReport, output_dir: &Path) -> Result<()> {
+    if schema.is_empty() {
+        return Ok(());
+    }
+    let path = pipeline::export_schema_report(config, schema, output_dir)?;
+    println!(
+        "The reservations payload changed: {} unknown, {} missing and {} invalid fields in {} records (see {})",
+        schema.unknown_fields.len(),
+        schema.missing_fields.len(),
+        schema.invalid_fields.len(),
+        schema.records,
+        path.display()
+    );
+    for (field, count) in &schema.missing_fields {
+        println!("  missing {} in {} records", field, count);
+    }
+    for (field, count) in &schema.invalid_fields {
+        println!("  invalid {} in {} records", field, count);
+    }
+    Ok(())
+}
+
+fn write_diff(config: &Config, diff: &ReservationDiff, output_dir: &Path) -> Result<()> {
+    let (xlsx_path, json_path) = pipeline::export_diff(config, diff, output_dir)?;
+    println!(
+        "Changes: {} new, {} cancelled, {} modified, {} disappeared",
+        diff.new, diff.cancelled, diff.modified, diff.disappeared
+    );
+    println!(
+        "The diff was written in {} and {}",
+        xlsx_path.display(),
+        json_path.display()
+    );
+    Ok(())
+}
+
+fn sync(config: &Config, args: &SyncArgs, output_dir: &Path) -> Result<Vec<Reservation>> {
+    let store_path = args
+        .store
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(&config.store_path));
+    let query = query(config, &args.fetch)?;
+    let mut store = Store::open(&store_path)?;
+    let mut source = open_source(config, &args.fetch, output_dir)?;
+    println!("Starting sync step...");
+    let (record, schema) = pipeline::sync(config, source.as_mut(), &mut store, &query, args.full)?;
+    println!(
+        "Fetched from {}: {} new, {} updated, {} unchanged ({} stored in {})",
+        record.fetched_from,
+        record.new,
+        record.updated,
+        record.unchanged,
+        store.len(),
+        store_path.display()
+    );
+    report_schema(config, &schema, output_dir)?;
+    Ok(store
+        .reservations()
+        .map(|stored| stored.reservation.clone())
+        .collect())
+}
+
+fn process(config: &Config, data: Vec<Reservation>) -> Result<DataFrame> {
+    println!("Starting processing step...");
+    let processed_df: DataFrame = pipeline::transform(config, data)?;
+    let mut cons_df = pipeline::commission(config, processed_df)?;
+    if config.reporting_currency.is_some() {
+        cons_df = pipeline::convert(config, cons_df, &pipeline::rate_table(config)?)?;
+    }
+    println!("Data has been processed...");
+    Ok(cons_df)
+}
+
+/// Checks the base workbook and writes the consolidated workbook with its
+/// summary and issues sheets; `period` is the fetch window, when known, for
+/// the occupancy rates.
+fn report(
+    config: &Config,
+    cons_df: DataFrame,
+    period: Option<(NaiveDate, NaiveDate)>,
+    output_dir: &Path,
+) -> Result<()> {
+    let issues = pipeline::validate(config, &cons_df)?;
+    for issue in &issues {
+        println!("{} {}: {}", issue.kind.label(), issue.key, issue.detail);
+    }
+    let summaries = pipeline::summarize(config, &cons_df, period)?;
+    let output_filepath =
+        pipeline::export(config, cons_df.clone(), &summaries, &issues, output_dir)?;
+
+    let sheets: Vec<&str> = summaries
+        .iter()
+        .map(|summary| summary.sheet.as_str())
+        .collect();
+    println!(
+        "The Excel file was generated in {} with the summaries {}",
+        output_filepath.display(),
+        sheets.join(", ")
+    );
+    println!("Showing the first 5 rows...");
+    println!("{:?}", cons_df.head(Some(5)));
+    Ok(())
+}
+
+fn statements(
+    config: &Config,
+    args: &StatementsArgs,
+    cons_df: &DataFrame,
+    output_dir: &Path,
+) -> Result<()> {
+    let period = match (&args.date_min, &args.date_max) {
+        (Some(date_min), Some(date_max)) => {
+            validate_range(date_min, Some(date_max))?;
+            ReservationQuery {
+                date_max: Some(date_max.clone()),
+                ..config.query(date_min)
+            }
+            .period()
+        }
+        _ => None,
+    };
+    let statements = pipeline::statements(config, cons_df, period)?;
+    let paths = pipeline::export_statements(config, &statements, output_dir)?;
+    for (statement, path) in statements.iter().zip(&paths) {
+        println!(
+            "{} reservations of {} in {}",
+            statement.reservations.height(),
+            statement.owner,
+            path.display()
+        );
+    }
+    println!("{} statements were generated", paths.len());
+    Ok(())
+}
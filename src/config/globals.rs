@@ -1,7 +1,19 @@
-use chrono::Local;
+use crate::error::{Error, Result};
+use crate::helpers::commission::CommissionDate;
+use crate::helpers::money::Rounding;
+use crate::models::query::{ReservationQuery, ReservationStatus};
+use crate::models::reservation::{DEFAULT_EXPORT_COLUMNS, RESERVATION_COLUMNS};
+use chrono::{Local, NaiveDate};
 use dotenv::dotenv;
 use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+/// Settings shared by every stage of the pipeline.
+///
+/// [`Config::from_env`] reads them from the environment (and `.env`); library
+/// users can also fill the fields directly.
+#[derive(Debug, Clone)]
 pub struct Config {
     pub example_csv: String,
     pub base_xlsx: String,
@@ -11,24 +23,196 @@ pub struct Config {
     pub output_file_prefix_1: String,
     pub output_file_prefix_2: String,
     pub datetime_suffix: String,
+    pub airbnb_base_url: String,
+    pub secrets_backend: String,
+    pub secrets_file: Option<String>,
+    pub ssm_prefix: Option<String>,
+    pub ssm_region: Option<String>,
+    pub ssm_profile: Option<String>,
+    pub aat_refresh_command: Option<String>,
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+    pub retry_max_delay_ms: u64,
+    pub requests_per_second: f64,
+    pub request_timeout_secs: u64,
+    pub store_path: String,
+    pub sync_lookback_days: i64,
+    pub query_statuses: Vec<ReservationStatus>,
+    pub query_listing_ids: Vec<String>,
+    pub query_currency: String,
+    pub query_locale: String,
+    pub query_page_size: u32,
+    /// Reservation columns of the exported workbooks, in order.
+    pub export_columns: Vec<String>,
+    pub commission_rounding: Rounding,
+    /// Sheet of the base workbook with the commission rules, if any.
+    pub commission_rules_sheet: Option<String>,
+    /// Date of the reservations that picks the commission agreement in force.
+    pub commission_date: CommissionDate,
+    /// Converts every amount into this currency when set.
+    pub reporting_currency: Option<String>,
+    pub fx_rates: Option<String>,
+    pub fx_rates_sheet: String,
+    /// Column of the base workbook with the zone of each listing.
+    pub zone_column: String,
+    /// Lowest name similarity, from 0 to 1, of a fuzzy listing match.
+    pub listing_match_threshold: f64,
+    /// Fails the report when the base workbook has issues instead of only
+    /// listing them.
+    pub strict_mapping: bool,
+    /// File name of the owner statements, without extension; see
+    /// [`STATEMENT_PLACEHOLDERS`].
+    pub statement_file_pattern: String,
+}
+
+/// Placeholders of `STATEMENT_FILE_PATTERN`: the owner, the first and last
+/// day of the period and the run timestamp.
+pub const STATEMENT_PLACEHOLDERS: [&str; 4] = ["{owner}", "{from}", "{to}", "{timestamp}"];
+
+fn optional_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+fn required_var(key: &str) -> Result<String> {
+    env::var(key).map_err(|_| Error::Config(format!("{} must be set", key)))
+}
+
+fn list_var(key: &str) -> Vec<String> {
+    optional_var(key)
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parsed_var<T: FromStr>(key: &str, default: T) -> Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    match optional_var(key) {
+        Some(value) => value
+            .parse()
+            .map_err(|e| Error::Config(format!("{} is not valid ({}): {}", key, value, e))),
+        None => Ok(default),
+    }
 }
 
 impl Config {
-    pub fn new() -> Self {
+    pub fn from_env() -> Result<Self> {
         dotenv().ok();
 
-        let example_csv: String = env::var("EXAMPLE_CSV").expect("EXAMPLE_CSV must be set");
-        let base_xlsx: String = env::var("BASE_XLSX").expect("BASE_XLSX must be set");
-        let first_sheet: String = env::var("FIRST_SHEET").expect("FIRST_SHEET must be set");
-        let second_sheet: String = env::var("SECOND_SHEET").expect("SECOND_SHEET must be set");
-        let third_sheet: String = env::var("THIRD_SHEET").expect("THIRD_SHEET must be set");
-        let output_file_prefix_1: String =
-            env::var("OUTPUT_FILE_PREFIX_1").expect("OUTPUT_FILE_PREFIX_1 must be set");
-        let output_file_prefix_2: String =
-            env::var("OUTPUT_FILE_PREFIX_2").expect("OUTPUT_FILE_PREFIX_2 must be set");
+        let example_csv: String = required_var("EXAMPLE_CSV")?;
+        let base_xlsx: String = required_var("BASE_XLSX")?;
+        let first_sheet: String = required_var("FIRST_SHEET")?;
+        let second_sheet: String = required_var("SECOND_SHEET")?;
+        let third_sheet: String = required_var("THIRD_SHEET")?;
+        let output_file_prefix_1: String = required_var("OUTPUT_FILE_PREFIX_1")?;
+        let output_file_prefix_2: String = required_var("OUTPUT_FILE_PREFIX_2")?;
         let datetime_suffix: String = Local::now().format("%Y%m%d%H%M%S").to_string();
+        let airbnb_base_url: String =
+            optional_var("AIRBNB_BASE_URL").unwrap_or_else(|| "https://www.airbnb.com".to_string());
+        let secrets_backend: String =
+            optional_var("SECRETS_BACKEND").unwrap_or_else(|| "ssm".to_string());
+        let secrets_file: Option<String> = optional_var("SECRETS_FILE");
+        let ssm_prefix: Option<String> = optional_var("SSM_PREFIX");
+        let ssm_region: Option<String> = optional_var("SSM_REGION");
+        let ssm_profile: Option<String> = optional_var("SSM_PROFILE");
+        let aat_refresh_command: Option<String> = optional_var("AAT_REFRESH_COMMAND");
+        let max_retries: u32 = parsed_var("MAX_RETRIES", 5)?;
+        let retry_base_delay_ms: u64 = parsed_var("RETRY_BASE_DELAY_MS", 500)?;
+        let retry_max_delay_ms: u64 = parsed_var("RETRY_MAX_DELAY_MS", 30_000)?;
+        let requests_per_second: f64 = parsed_var("REQUESTS_PER_SECOND", 1.0)?;
+        let request_timeout_secs: u64 = parsed_var("REQUEST_TIMEOUT_SECS", 30)?;
+        let store_path: String =
+            optional_var("STORE_PATH").unwrap_or_else(|| "reservations.jsonl".to_string());
+        let sync_lookback_days: i64 = parsed_var("SYNC_LOOKBACK_DAYS", 30)?;
+        let defaults = ReservationQuery::new("");
+        let query_statuses: Vec<ReservationStatus> = match list_var("QUERY_STATUSES") {
+            statuses if statuses.is_empty() => defaults.statuses,
+            statuses => statuses
+                .iter()
+                .map(|status| status.parse())
+                .collect::<Result<_>>()?,
+        };
+        let query_listing_ids: Vec<String> = list_var("QUERY_LISTING_IDS");
+        let query_currency: String = optional_var("QUERY_CURRENCY").unwrap_or(defaults.currency);
+        let query_locale: String = optional_var("QUERY_LOCALE").unwrap_or(defaults.locale);
+        let query_page_size: u32 = parsed_var("QUERY_PAGE_SIZE", defaults.page_size)?;
+        if query_page_size == 0 {
+            return Err(Error::Config(
+                "QUERY_PAGE_SIZE must be positive".to_string(),
+            ));
+        }
+        let export_columns: Vec<String> = match list_var("EXPORT_COLUMNS") {
+            columns if columns.is_empty() => DEFAULT_EXPORT_COLUMNS
+                .iter()
+                .map(|column| column.to_string())
+                .collect(),
+            columns => columns,
+        };
+        if let Some(unknown) = export_columns
+            .iter()
+            .find(|name| !RESERVATION_COLUMNS.iter().any(|(column, _)| column == name))
+        {
+            let known: Vec<&str> = RESERVATION_COLUMNS
+                .iter()
+                .map(|(column, _)| *column)
+                .collect();
+            return Err(Error::Config(format!(
+                "Unknown export column: {} (expected one of {})",
+                unknown,
+                known.join(", ")
+            )));
+        }
+        let commission_rounding: Rounding = parsed_var("COMMISSION_ROUNDING", Rounding::default())?;
+        let commission_rules_sheet: Option<String> = optional_var("COMMISSION_RULES_SHEET");
+        let commission_date: CommissionDate =
+            parsed_var("COMMISSION_DATE", CommissionDate::default())?;
+        let reporting_currency: Option<String> =
+            optional_var("REPORTING_CURRENCY").map(|currency| currency.to_uppercase());
+        let fx_rates: Option<String> = optional_var("FX_RATES");
+        let fx_rates_sheet: String =
+            optional_var("FX_RATES_SHEET").unwrap_or_else(|| "rates".to_string());
+        let zone_column: String = optional_var("ZONE_COLUMN").unwrap_or_else(|| "ZONA".to_string());
+        let listing_match_threshold: f64 = parsed_var("LISTING_MATCH_THRESHOLD", 0.8)?;
+        if !(0.0..=1.0).contains(&listing_match_threshold) {
+            return Err(Error::Config(format!(
+                "LISTING_MATCH_THRESHOLD must be between 0 and 1, got {}",
+                listing_match_threshold
+            )));
+        }
+        let strict_mapping: bool = parsed_var("STRICT_MAPPING", false)?;
+        let statement_file_pattern: String = optional_var("STATEMENT_FILE_PATTERN")
+            .unwrap_or_else(|| "statement_{owner}_{timestamp}".to_string());
+        if !statement_file_pattern.contains("{owner}") {
+            return Err(Error::Config(
+                "STATEMENT_FILE_PATTERN must contain {owner}".to_string(),
+            ));
+        }
+        let unknown = STATEMENT_PLACEHOLDERS
+            .iter()
+            .fold(statement_file_pattern.clone(), |rest, placeholder| {
+                rest.replace(placeholder, "")
+            });
+        if unknown.contains(['{', '}']) {
+            return Err(Error::Config(format!(
+                "Unknown placeholder in STATEMENT_FILE_PATTERN: {} (expected {})",
+                statement_file_pattern,
+                STATEMENT_PLACEHOLDERS.join(", ")
+            )));
+        }
+        if reporting_currency.is_some() && fx_rates.is_none() {
+            return Err(Error::Config(
+                "FX_RATES must be set when REPORTING_CURRENCY is".to_string(),
+            ));
+        }
 
-        Config {
+        Ok(Config {
             example_csv,
             base_xlsx,
             first_sheet,
@@ -37,10 +221,85 @@ impl Config {
             output_file_prefix_1,
             output_file_prefix_2,
             datetime_suffix,
+            airbnb_base_url,
+            secrets_backend,
+            secrets_file,
+            ssm_prefix,
+            ssm_region,
+            ssm_profile,
+            aat_refresh_command,
+            max_retries,
+            retry_base_delay_ms,
+            retry_max_delay_ms,
+            requests_per_second,
+            request_timeout_secs,
+            store_path,
+            sync_lookback_days,
+            query_statuses,
+            query_listing_ids,
+            query_currency,
+            query_locale,
+            query_page_size,
+            export_columns,
+            commission_rounding,
+            commission_rules_sheet,
+            commission_date,
+            reporting_currency,
+            fx_rates,
+            fx_rates_sheet,
+            zone_column,
+            listing_match_threshold,
+            strict_mapping,
+            statement_file_pattern,
+        })
+    }
+
+    /// Fetch from `date_min` on with the configured `QUERY_*` filters.
+    pub fn query(&self, date_min: &str) -> ReservationQuery {
+        ReservationQuery {
+            date_min: date_min.to_string(),
+            date_max: None,
+            statuses: self.query_statuses.clone(),
+            listing_ids: self.query_listing_ids.clone(),
+            currency: self.query_currency.clone(),
+            locale: self.query_locale.clone(),
+            page_size: self.query_page_size,
         }
     }
-}
 
-lazy_static! {
-    pub static ref CONFIG: Config = Config::new();
+    /// `{output_dir}/{prefix}_{datetime_suffix}.{extension}`
+    pub fn output_path(&self, output_dir: &Path, prefix: &str, extension: &str) -> PathBuf {
+        output_dir.join(format!(
+            "{}_{}.{}",
+            prefix,
+            self.datetime_suffix.as_str(),
+            extension
+        ))
+    }
+
+    /// `{output_dir}/{statement_file_pattern}.xlsx` for `owner`, with the
+    /// characters file names cannot have replaced by `_`.
+    pub fn statement_path(
+        &self,
+        output_dir: &Path,
+        owner: &str,
+        period: Option<(NaiveDate, NaiveDate)>,
+    ) -> PathBuf {
+        let day = |date: Option<NaiveDate>| date.map(|date| date.to_string()).unwrap_or_default();
+        let name = self
+            .statement_file_pattern
+            .replace("{owner}", owner)
+            .replace("{from}", &day(period.map(|(from, _)| from)))
+            .replace("{to}", &day(period.map(|(_, to)| to)))
+            .replace("{timestamp}", &self.datetime_suffix);
+        let name: String = name
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect();
+        output_dir.join(format!("{}.xlsx", name))
+    }
 }
@@ -1 +1,3 @@
 pub mod globals;
+
+pub use globals::Config;
@@ -0,0 +1,102 @@
+use polars::prelude::PolarsError;
+use reqwest::StatusCode;
+use std::io;
+use thiserror::Error;
+use xlsxwriter::XlsxError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    #[error("Could not read secret {key}: {message}")]
+    Secrets { key: String, message: String },
+
+    #[error("The service is disabled")]
+    ServiceDisabled,
+
+    #[error("Request to {url} failed: {source}")]
+    Http {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("Request to {url} failed with status code {status}")]
+    HttpStatus { url: String, status: StatusCode },
+
+    #[error(
+        "Airbnb rejected {credential} with 401 Unauthorized ({})",
+        if *refreshed { "still stale after refreshing it" } else { "no refresh is configured" }
+    )]
+    Auth { credential: String, refreshed: bool },
+
+    #[error("Unexpected API payload: {0}")]
+    Schema(String),
+
+    #[error("Could not parse {what}: {message}")]
+    Parse { what: String, message: String },
+
+    #[error("Could not read workbook {path}: {message}")]
+    ExcelRead { path: String, message: String },
+
+    #[error("Could not write workbook {path}: {source}")]
+    ExcelWrite {
+        path: String,
+        #[source]
+        source: XlsxError,
+    },
+
+    #[error("Invalid sheet {sheet} in the base workbook: {message}")]
+    MappingSheet { sheet: String, message: String },
+
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error(transparent)]
+    Polars(#[from] PolarsError),
+}
+
+impl Error {
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        Error::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub fn parse(what: impl Into<String>, message: impl ToString) -> Self {
+        Error::Parse {
+            what: what.into(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn mapping_sheet(sheet: &str, message: impl Into<String>) -> Self {
+        Error::MappingSheet {
+            sheet: sheet.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Process exit code, one per failure family so wrapper scripts can tell
+    /// a disabled service from an expired token or a bad base workbook.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Config(_) => 2,
+            Error::ServiceDisabled => 3,
+            Error::Secrets { .. } => 4,
+            Error::Auth { .. } => 5,
+            Error::Http { .. } | Error::HttpStatus { .. } => 6,
+            Error::Schema(_) | Error::Parse { .. } => 7,
+            Error::ExcelRead { .. } | Error::MappingSheet { .. } => 8,
+            Error::ExcelWrite { .. } | Error::Io { .. } | Error::Polars(_) => 9,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Typed cells of DataFrame columns, for the helpers that work row by row.
+use super::dates::from_epoch_days;
+use crate::error::Result;
+use chrono::NaiveDate;
+use polars::prelude::*;
+
+/// Trimmed text cells of a column, blanks as `None`, all `None` when the
+/// frame does not have it.
+pub fn text_column(df: &DataFrame, name: &str) -> Result<Vec<Option<String>>> {
+    match df.column(name) {
+        Ok(column) => Ok(column
+            .cast(&DataType::String)?
+            .str()?
+            .into_iter()
+            .map(|value| {
+                value
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+            })
+            .collect()),
+        Err(_) => Ok(vec![None; df.height()]),
+    }
+}
+
+/// Cells of a `Date` column.
+pub fn date_column(df: &DataFrame, name: &str) -> Result<Vec<Option<NaiveDate>>> {
+    let days = df.column(name)?.cast(&DataType::Int32)?;
+    Ok(days
+        .i32()?
+        .into_iter()
+        .map(|days| days.and_then(from_epoch_days))
+        .collect())
+}
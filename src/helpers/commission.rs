@@ -0,0 +1,601 @@
+//! Commission rules: the rate, fixed fee, minimum and cap that apply to each
+//! reservation, by listing, owner, zone or by default, and the dates each
+//! agreement is valid.
+use super::columns::{date_column, text_column};
+use super::dates::sheet_date;
+use super::money::{apply_rate, cents_to_amounts, parse_money, to_cents, Rounding};
+use crate::error::{Error, Result};
+use chrono::NaiveDate;
+use polars::prelude::*;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// What a rule applies to, from the most to the least specific.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuleScope {
+    Listing,
+    Owner,
+    Zone,
+    Default,
+}
+
+impl RuleScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleScope::Listing => "listing",
+            RuleScope::Owner => "owner",
+            RuleScope::Zone => "zone",
+            RuleScope::Default => "default",
+        }
+    }
+}
+
+impl fmt::Display for RuleScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RuleScope {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "listing" => Ok(RuleScope::Listing),
+            "owner" => Ok(RuleScope::Owner),
+            "zone" => Ok(RuleScope::Zone),
+            "default" => Ok(RuleScope::Default),
+            other => Err(Error::parse(
+                "commission scope",
+                format!("{:?} is not listing, owner, zone or default", other),
+            )),
+        }
+    }
+}
+
+/// Amount a commission rate applies to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommissionBasis {
+    /// The earnings.
+    #[default]
+    Gross,
+    /// The earnings less the cleaning fee.
+    NetOfCleaning,
+    /// The earnings less the taxes.
+    NetOfTaxes,
+}
+
+impl CommissionBasis {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommissionBasis::Gross => "gross",
+            CommissionBasis::NetOfCleaning => "net-of-cleaning",
+            CommissionBasis::NetOfTaxes => "net-of-taxes",
+        }
+    }
+}
+
+impl fmt::Display for CommissionBasis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CommissionBasis {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "gross" => Ok(CommissionBasis::Gross),
+            "net-of-cleaning" => Ok(CommissionBasis::NetOfCleaning),
+            "net-of-taxes" => Ok(CommissionBasis::NetOfTaxes),
+            other => Err(Error::parse(
+                "commission basis",
+                format!("{:?} is not gross, net-of-cleaning or net-of-taxes", other),
+            )),
+        }
+    }
+}
+
+/// Date of a reservation that picks the agreement in force, set by
+/// `COMMISSION_DATE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommissionDate {
+    Booked,
+    #[default]
+    CheckIn,
+    CheckOut,
+}
+
+impl CommissionDate {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommissionDate::Booked => "booked",
+            CommissionDate::CheckIn => "check-in",
+            CommissionDate::CheckOut => "check-out",
+        }
+    }
+
+    /// Frame column with this date.
+    pub fn column(&self) -> &'static str {
+        match self {
+            CommissionDate::Booked => "booked_date",
+            CommissionDate::CheckIn => "start_date",
+            CommissionDate::CheckOut => "end_date",
+        }
+    }
+}
+
+impl fmt::Display for CommissionDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CommissionDate {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "booked" => Ok(CommissionDate::Booked),
+            "check-in" => Ok(CommissionDate::CheckIn),
+            "check-out" => Ok(CommissionDate::CheckOut),
+            other => Err(Error::Config(format!(
+                "Unknown commission date: {} (expected booked, check-in or check-out)",
+                other
+            ))),
+        }
+    }
+}
+
+/// One rate of the commission rules, or one tier of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommissionRule {
+    /// Recorded in `commission_rule` for every reservation it applies to.
+    pub id: String,
+    /// Agreement the rule belongs to, recorded in `commission_agreement`;
+    /// the tiers of an agreement share it.
+    pub agreement: String,
+    pub scope: RuleScope,
+    /// Listing name, owner or zone; ignored by the default rules.
+    pub key: String,
+    /// e.g. `0.15` for 15%.
+    pub rate: Decimal,
+    pub basis: CommissionBasis,
+    /// Monthly earnings, in cents, from which this tier applies.
+    pub min_volume_cents: i64,
+    /// Added to every reservation, in cents.
+    pub fixed_fee_cents: i64,
+    pub minimum_cents: Option<i64>,
+    pub cap_cents: Option<i64>,
+    /// First and last days the rule is in force, both included; open ended
+    /// when unset.
+    pub valid_from: Option<NaiveDate>,
+    pub valid_to: Option<NaiveDate>,
+}
+
+impl CommissionRule {
+    /// `rate` of the gross earnings, always in force, without fee, minimum
+    /// or cap, as its own agreement.
+    pub fn new(id: &str, scope: RuleScope, key: &str, rate: Decimal) -> Self {
+        CommissionRule {
+            id: id.to_string(),
+            agreement: id.to_string(),
+            scope,
+            key: key.to_string(),
+            rate,
+            basis: CommissionBasis::Gross,
+            min_volume_cents: 0,
+            fixed_fee_cents: 0,
+            minimum_cents: None,
+            cap_cents: None,
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    /// Commission on `base_cents`: the rate plus the fixed fee, raised to the
+    /// minimum when the base is positive (refunds keep their negative
+    /// commission) and lowered to the cap.
+    pub fn commission(&self, base_cents: i64, rounding: Rounding) -> Result<i64> {
+        let mut cents = apply_rate(base_cents, self.rate, rounding)? + self.fixed_fee_cents;
+        if let Some(minimum) = self.minimum_cents.filter(|_| base_cents > 0) {
+            cents = cents.max(minimum);
+        }
+        if let Some(cap) = self.cap_cents {
+            cents = cents.min(cap);
+        }
+        Ok(cents)
+    }
+
+    fn applies_to(&self, scope: RuleScope, key: Option<&str>) -> bool {
+        self.scope == scope && (scope == RuleScope::Default || key == Some(self.key.as_str()))
+    }
+
+    /// Whether the rule is in force on `date`; dated rules never apply to
+    /// reservations without the date.
+    fn in_force(&self, date: Option<NaiveDate>) -> bool {
+        match (self.valid_from, self.valid_to) {
+            (None, None) => true,
+            (from, to) => date.is_some_and(|date| {
+                from.is_none_or(|from| from <= date) && to.is_none_or(|to| date <= to)
+            }),
+        }
+    }
+
+    /// Whether both rules could apply to the same reservation as the same
+    /// tier.
+    fn overlaps(&self, other: &CommissionRule) -> bool {
+        let starts_before_end = |rule: &CommissionRule, end: Option<NaiveDate>| {
+            rule.valid_from
+                .zip(end)
+                .is_none_or(|(from, end)| from <= end)
+        };
+        self.scope == other.scope
+            && (self.scope == RuleScope::Default || self.key == other.key)
+            && self.min_volume_cents == other.min_volume_cents
+            && starts_before_end(self, other.valid_to)
+            && starts_before_end(other, self.valid_to)
+    }
+}
+
+/// The first pair of `rules` that overlap, as an error message.
+fn overlapping(rules: &[CommissionRule]) -> std::result::Result<(), String> {
+    for (index, rule) in rules.iter().enumerate() {
+        if let Some(other) = rules[index + 1..].iter().find(|other| rule.overlaps(other)) {
+            return Err(format!(
+                "the {} rules {} and {} overlap; give them distinct valid_from and valid_to dates",
+                rule.scope, rule.id, other.id
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Text cells of the `required` and `optional` columns of a sheet.
+fn sheet_columns<'a>(
+    df: &DataFrame,
+    required: &[&'a str],
+    optional: &[&'a str],
+) -> std::result::Result<HashMap<&'a str, Vec<Option<String>>>, String> {
+    if let Some(name) = required.iter().find(|name| df.column(name).is_err()) {
+        return Err(format!("missing column {}", name));
+    }
+    required
+        .iter()
+        .chain(optional)
+        .map(|name| text_column(df, name).map(|values| (*name, values)))
+        .collect::<Result<_>>()
+        .map_err(|e| e.to_string())
+}
+
+/// A sheet cell as an exact decimal, plain or in scientific notation.
+pub fn decimal(value: &str) -> Option<Decimal> {
+    Decimal::from_str(value)
+        .or_else(|_| Decimal::from_scientific(value))
+        .ok()
+}
+
+/// The `valid_from` and `valid_to` cells of a row, `line` being its line in
+/// the sheet.
+fn validity<'a>(
+    cell: &impl Fn(&str) -> Option<&'a str>,
+    line: usize,
+) -> std::result::Result<(Option<NaiveDate>, Option<NaiveDate>), String> {
+    let date = |name: &str| {
+        cell(name)
+            .map(|value| {
+                sheet_date(value)
+                    .ok_or_else(|| format!("row {}: {} {:?} is not a date", line, name, value))
+            })
+            .transpose()
+    };
+    let (valid_from, valid_to) = (date("valid_from")?, date("valid_to")?);
+    if let (Some(from), Some(to)) = (valid_from, valid_to) {
+        if to < from {
+            return Err(format!(
+                "row {}: valid_to {} is before valid_from {}",
+                line, to, from
+            ));
+        }
+    }
+    Ok((valid_from, valid_to))
+}
+
+/// One row of a rules sheet, `line` being its line in the sheet.
+fn rule<'a>(
+    cell: impl Fn(&str) -> Option<&'a str>,
+    line: usize,
+) -> std::result::Result<CommissionRule, String> {
+    let cents = |name: &str| -> std::result::Result<Option<i64>, String> {
+        cell(name)
+            .map(|value| {
+                decimal(value)
+                    .and_then(|amount| to_cents(amount, Rounding::HalfUp).ok())
+                    .ok_or_else(|| format!("row {}: {} {:?} is not an amount", line, name, value))
+            })
+            .transpose()
+    };
+
+    let id = cell("id").ok_or_else(|| format!("row {}: the rule has no id", line))?;
+    let scope: RuleScope = cell("scope")
+        .unwrap_or_default()
+        .parse()
+        .map_err(|e| format!("row {}: {}", line, e))?;
+    let key = cell("key").unwrap_or_default();
+    if scope != RuleScope::Default && key.is_empty() {
+        return Err(format!(
+            "row {}: the {} rule {} has no key",
+            line, scope, id
+        ));
+    }
+    let rate = cell("rate")
+        .and_then(decimal)
+        .ok_or_else(|| format!("row {}: {:?} is not a rate", line, cell("rate")))?;
+    let basis: CommissionBasis = cell("basis")
+        .unwrap_or_default()
+        .parse()
+        .map_err(|e| format!("row {}: {}", line, e))?;
+    let (valid_from, valid_to) = validity(&cell, line)?;
+    Ok(CommissionRule {
+        agreement: cell("agreement").unwrap_or(id).to_string(),
+        basis,
+        valid_from,
+        valid_to,
+        min_volume_cents: cents("min_volume")?.unwrap_or_default(),
+        fixed_fee_cents: cents("fixed_fee")?.unwrap_or_default(),
+        minimum_cents: cents("minimum")?,
+        cap_cents: cents("cap")?,
+        ..CommissionRule::new(id, scope, key, rate)
+    })
+}
+
+/// Rules by listing, owner, zone and default.
+///
+/// A reservation gets the rules of its listing, or else of its owner, its
+/// zone or the default ones, among those in force on its reference date.
+/// When several tiers share a key, the one with the highest
+/// `min_volume_cents` the month's earnings reach applies; the earnings are
+/// those of the listing, owner or zone in the month of the reference date and
+/// currency of the reservation (of its listing for the default rules).
+#[derive(Debug, Clone, Default)]
+pub struct CommissionRules {
+    rules: Vec<CommissionRule>,
+}
+
+impl CommissionRules {
+    pub fn new(rules: Vec<CommissionRule>) -> Self {
+        CommissionRules { rules }
+    }
+
+    pub fn push(&mut self, rule: CommissionRule) {
+        self.rules.push(rule);
+    }
+
+    /// Whether some rule applies to `key` in `scope`.
+    pub fn covers(&self, scope: RuleScope, key: &str) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.applies_to(scope, Some(key)))
+    }
+
+    /// Reads a rules sheet with `id`, `scope`, `key` and `rate` columns and
+    /// the optional `agreement`, `basis`, `min_volume`, `fixed_fee`,
+    /// `minimum`, `cap`, `valid_from` and `valid_to` columns, whose amounts
+    /// are in currency units. Rules of the same tier must not overlap in time.
+    pub fn from_dataframe(df: &DataFrame) -> std::result::Result<Self, String> {
+        let columns = sheet_columns(
+            df,
+            &["id", "scope", "rate"],
+            &[
+                "key",
+                "agreement",
+                "basis",
+                "min_volume",
+                "fixed_fee",
+                "minimum",
+                "cap",
+                "valid_from",
+                "valid_to",
+            ],
+        )?;
+        let rules: Vec<CommissionRule> = (0..df.height())
+            .map(|row| rule(|name| columns[name][row].as_deref(), row + 2))
+            .collect::<std::result::Result<_, _>>()?;
+        overlapping(&rules)?;
+        Ok(CommissionRules::new(rules))
+    }
+
+    /// Owner rates of a mapping sheet with `PROPIETARIO` and `Comision`
+    /// columns and the optional `agreement`, `valid_from` and `valid_to`
+    /// columns, for the owners no rule covers yet. Rows without a rate are
+    /// skipped, so their owners fall back to the default rules.
+    pub fn push_owner_rates(&mut self, df: &DataFrame) -> std::result::Result<(), String> {
+        let columns = sheet_columns(
+            df,
+            &["PROPIETARIO", "Comision"],
+            &["agreement", "valid_from", "valid_to"],
+        )?;
+        let mut rates: Vec<CommissionRule> = Vec::new();
+        let cell_of = |row: usize, name: &str| columns[name][row].as_deref();
+        for row in 0..df.height() {
+            let cell = |name: &str| cell_of(row, name);
+            let (Some(owner), Some(rate)) =
+                (cell("PROPIETARIO"), cell("Comision").and_then(decimal))
+            else {
+                continue;
+            };
+            if self.covers(RuleScope::Owner, owner) {
+                continue;
+            }
+            let (valid_from, valid_to) = validity(&cell, row + 2)?;
+            let id = match valid_from {
+                Some(from) => format!("owner:{}:{}", owner, from),
+                None => format!("owner:{}", owner),
+            };
+            rates.push(CommissionRule {
+                agreement: cell("agreement").unwrap_or(&id).to_string(),
+                valid_from,
+                valid_to,
+                ..CommissionRule::new(&id, RuleScope::Owner, owner, rate)
+            });
+        }
+        overlapping(&rates)?;
+        self.rules.extend(rates);
+        Ok(())
+    }
+
+    /// The tier of the most specific rules for `keys` in force on `date` and
+    /// reached by `volume`.
+    fn find(
+        &self,
+        keys: &[(RuleScope, Option<&str>)],
+        date: Option<NaiveDate>,
+        volume: impl Fn(RuleScope) -> i64,
+    ) -> Option<&CommissionRule> {
+        keys.iter().find_map(|(scope, key)| {
+            let volume = volume(*scope);
+            self.rules
+                .iter()
+                .filter(|rule| {
+                    rule.applies_to(*scope, *key)
+                        && rule.in_force(date)
+                        && rule.min_volume_cents <= volume
+                })
+                .max_by_key(|rule| rule.min_volume_cents)
+        })
+    }
+
+    /// Adds the `Comision` rate, the `commission_rule` and
+    /// `commission_agreement` that produced it and the `commission_cents` and
+    /// `commission_earnings` of every row, keeping the other columns.
+    /// `zone_column` holds the zone of each listing, and the agreements in
+    /// force on the `reference` date of a reservation apply to it. Rows
+    /// without an amount get no commission, and so do the rows without a rule
+    /// or with a fee that does not parse, which are marked with the reason in
+    /// a `commission_error` column, added when any row fails.
+    pub fn apply(
+        &self,
+        mut df: DataFrame,
+        zone_column: &str,
+        reference: CommissionDate,
+        rounding: Rounding,
+    ) -> Result<DataFrame> {
+        let currencies = df.column("currency")?.str()?;
+        let amounts = df.column("amount_cents")?.i64()?;
+        // Canonical names of the matched listings, so renames keep their rules
+        let listings: Vec<Option<String>> = text_column(&df, "ANUNCIO")?
+            .into_iter()
+            .zip(text_column(&df, "listing_name")?)
+            .map(|(canonical, name)| canonical.or(name))
+            .collect();
+        let owners = text_column(&df, "PROPIETARIO")?;
+        let zones = text_column(&df, zone_column)?;
+        let cleaning_fees = text_column(&df, "payout_breakdown_cleaning_fee")?;
+        let taxes = text_column(&df, "payout_breakdown_taxes")?;
+        let dates = date_column(&df, reference.column())?;
+        let months: Vec<String> = dates
+            .iter()
+            .map(|date| {
+                date.map(|date| date.format("%Y-%m").to_string())
+                    .unwrap_or_default()
+            })
+            .collect();
+        let keys_of = |row: usize| {
+            [
+                (RuleScope::Listing, listings[row].as_deref()),
+                (RuleScope::Owner, owners[row].as_deref()),
+                (RuleScope::Zone, zones[row].as_deref()),
+                (RuleScope::Default, listings[row].as_deref()),
+            ]
+        };
+
+        // Monthly earnings of every listing, owner and zone
+        let mut volumes: HashMap<(RuleScope, &str, &str, &str), i64> = HashMap::new();
+        for (row, month) in months.iter().enumerate() {
+            let currency = currencies.get(row).unwrap_or_default();
+            for (scope, key) in &keys_of(row)[..3] {
+                if let Some(key) = key {
+                    *volumes.entry((*scope, key, month, currency)).or_default() +=
+                        amounts.get(row).unwrap_or_default();
+                }
+            }
+        }
+
+        let mut rates: Vec<Option<f64>> = Vec::with_capacity(df.height());
+        let mut rule_ids: Vec<Option<String>> = Vec::with_capacity(df.height());
+        let mut agreements: Vec<Option<String>> = Vec::with_capacity(df.height());
+        let mut cents: Vec<Option<i64>> = Vec::with_capacity(df.height());
+        let mut errors: Vec<Option<String>> = Vec::with_capacity(df.height());
+        for row in 0..df.height() {
+            let currency = currencies.get(row).unwrap_or_default();
+            // Earnings that did not parse are reported by the validation
+            let Some(amount) = amounts.get(row) else {
+                rates.push(None);
+                rule_ids.push(None);
+                agreements.push(None);
+                cents.push(None);
+                errors.push(None);
+                continue;
+            };
+            let keys = keys_of(row);
+            let volume = |scope: RuleScope| {
+                let scope = match scope {
+                    RuleScope::Default => RuleScope::Listing,
+                    scope => scope,
+                };
+                keys.iter()
+                    .find(|(key_scope, _)| *key_scope == scope)
+                    .and_then(|(_, key)| volumes.get(&(scope, (*key)?, &months[row], currency)))
+                    .copied()
+                    .unwrap_or(amount)
+            };
+            let fee = |value: &Option<String>| match value {
+                Some(value) => parse_money(value, currency)
+                    .and_then(|money| to_cents(money.amount, Rounding::HalfUp)),
+                None => Ok(0),
+            };
+
+            let commission = self
+                .find(&keys, dates[row], volume)
+                .ok_or_else(|| Error::parse("commission", "no commission rule applies"))
+                .and_then(|rule| {
+                    let base = match rule.basis {
+                        CommissionBasis::Gross => amount,
+                        CommissionBasis::NetOfCleaning => amount - fee(&cleaning_fees[row])?,
+                        CommissionBasis::NetOfTaxes => amount - fee(&taxes[row])?,
+                    };
+                    Ok((rule, rule.commission(base, rounding)?))
+                });
+            match commission {
+                Ok((rule, commission)) => {
+                    rates.push(rule.rate.to_f64());
+                    rule_ids.push(Some(rule.id.clone()));
+                    agreements.push(Some(rule.agreement.clone()));
+                    cents.push(Some(commission));
+                    errors.push(None);
+                }
+                Err(e) => {
+                    rates.push(None);
+                    rule_ids.push(None);
+                    agreements.push(None);
+                    cents.push(None);
+                    errors.push(Some(e.to_string()));
+                }
+            }
+        }
+
+        df.with_column(Series::new("Comision", rates))?;
+        df.with_column(Series::new("commission_rule", rule_ids))?;
+        df.with_column(Series::new("commission_agreement", agreements))?;
+        df.with_column(Series::new("commission_earnings", cents_to_amounts(&cents)))?;
+        df.with_column(Series::new("commission_cents", cents))?;
+        if errors.iter().any(Option::is_some) {
+            df.with_column(Series::new("commission_error", errors))?;
+        }
+        Ok(df)
+    }
+}
@@ -0,0 +1,121 @@
+use crate::error::{Error, Result};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+
+/// Date format of the `date_min`/`date_max` query parameters.
+pub const API_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Days from 0001-01-01 to 1970-01-01, the epoch of polars dates.
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+const ACCEPTED_FORMATS: &str = "YYYY-MM-DD, DD/MM/YYYY, DD-MM-YYYY, today, yesterday, \
+     -<n>d, -<n>w, -<n>m, this-month, last-month, this-year or last-year";
+
+/// Which end of a period a relative input such as `last-month` resolves to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateBound {
+    Start,
+    End,
+}
+
+/// A polars `Date` value, stored as days since 1970-01-01.
+pub fn from_epoch_days(days: i32) -> Option<NaiveDate> {
+    NaiveDate::from_num_days_from_ce_opt(days.checked_add(UNIX_EPOCH_DAYS_FROM_CE)?)
+}
+
+/// `YYYY-MM-DD`, or the serial number of an Excel date cell; `None` when the
+/// serial is not finite or out of the range of dates.
+pub fn sheet_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text, API_DATE_FORMAT)
+        .ok()
+        .or_else(|| {
+            let serial = text
+                .parse::<f64>()
+                .ok()
+                .filter(|serial| (i64::MIN as f64..i64::MAX as f64).contains(serial))?;
+            NaiveDate::from_ymd_opt(1899, 12, 30)?
+                .checked_add_signed(Duration::try_days(serial as i64)?)
+        })
+}
+
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+fn period(start: NaiveDate, months: u32, bound: DateBound) -> Option<NaiveDate> {
+    match bound {
+        DateBound::Start => Some(start),
+        DateBound::End => start.checked_add_months(Months::new(months))?.pred_opt(),
+    }
+}
+
+fn relative(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let amount = input.strip_prefix('-')?;
+    let unit = amount.chars().last()?;
+    let count: u32 = amount[..amount.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        'd' => today.checked_sub_signed(Duration::days(count.into())),
+        'w' => today.checked_sub_signed(Duration::weeks(count.into())),
+        'm' => today.checked_sub_months(Months::new(count)),
+        _ => None,
+    }
+}
+
+fn named(input: &str, bound: DateBound, today: NaiveDate) -> Option<NaiveDate> {
+    let this_month = month_start(today);
+    let this_year = NaiveDate::from_ymd_opt(today.year(), 1, 1)?;
+    match input {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        "this-month" => period(this_month, 1, bound),
+        "last-month" => period(this_month.checked_sub_months(Months::new(1))?, 1, bound),
+        "this-year" => period(this_year, 12, bound),
+        "last-year" => period(this_year.with_year(today.year() - 1)?, 12, bound),
+        _ => None,
+    }
+}
+
+/// Parses a user supplied date relative to `today`.
+///
+/// Period names resolve to their first day as a `Start` bound and to their
+/// last day as an `End` bound, so `--date-min last-month --date-max last-month`
+/// is exactly last month.
+pub fn parse_date(input: &str, bound: DateBound, today: NaiveDate) -> Result<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(&input, format).ok())
+        .or_else(|| relative(&input, today))
+        .or_else(|| named(&input, bound, today))
+        .ok_or_else(|| {
+            Error::parse(
+                "date",
+                format!("'{}' is not a date (use {})", input, ACCEPTED_FORMATS),
+            )
+        })
+}
+
+/// [`parse_date`] normalized to the format the API expects.
+pub fn normalize_date(input: &str, bound: DateBound, today: NaiveDate) -> Result<String> {
+    Ok(parse_date(input, bound, today)?
+        .format(API_DATE_FORMAT)
+        .to_string())
+}
+
+/// Checks that both bounds are API dates and that the window is not empty.
+pub fn validate_range(date_min: &str, date_max: Option<&str>) -> Result<()> {
+    let api_date = |what: &str, value: &str| {
+        NaiveDate::parse_from_str(value, API_DATE_FORMAT)
+            .map_err(|_| Error::parse(what, format!("'{}' is not a YYYY-MM-DD date", value)))
+    };
+    let min = api_date("date_min", date_min)?;
+    if let Some(date_max) = date_max {
+        let max = api_date("date_max", date_max)?;
+        if max < min {
+            return Err(Error::parse(
+                "date_max",
+                format!("{} is before date_min {}", date_max, date_min),
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use crate::models::reservation::Reservation;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// Only in the current snapshot.
+    New,
+    /// Status moved to a cancelled state.
+    Cancelled,
+    /// Any other field changed.
+    Modified,
+    /// Only in the previous snapshot.
+    Disappeared,
+}
+
+impl ChangeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChangeKind::New => "New",
+            ChangeKind::Cancelled => "Cancelled",
+            ChangeKind::Modified => "Modified",
+            ChangeKind::Disappeared => "Disappeared",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReservationChange {
+    pub confirmation_code: String,
+    pub kind: ChangeKind,
+    pub listing_name: String,
+    /// Field-level before/after, only for cancelled and modified reservations.
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReservationDiff {
+    pub new: usize,
+    pub cancelled: usize,
+    pub modified: usize,
+    pub disappeared: usize,
+    pub reservations: Vec<ReservationChange>,
+}
+
+impl ReservationDiff {
+    pub fn is_empty(&self) -> bool {
+        self.reservations.is_empty()
+    }
+}
+
+fn is_cancelled(status: &str) -> bool {
+    status.to_lowercase().contains("cancel")
+}
+
+fn by_code(reservations: &[Reservation]) -> BTreeMap<&str, &Reservation> {
+    reservations
+        .iter()
+        .map(|reservation| (reservation.confirmation_code.as_str(), reservation))
+        .collect()
+}
+
+fn field_changes(previous: &Reservation, current: &Reservation) -> Vec<FieldChange> {
+    let (Ok(Value::Object(before)), Ok(Value::Object(after))) = (
+        serde_json::to_value(previous),
+        serde_json::to_value(current),
+    ) else {
+        return Vec::new();
+    };
+    after
+        .into_iter()
+        .filter_map(|(field, after)| {
+            let before = before.get(&field).cloned().unwrap_or(Value::Null);
+            (before != after).then_some(FieldChange {
+                field,
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+/// Compares two snapshots by `confirmation_code`, sorted by code.
+pub fn diff_reservations(previous: &[Reservation], current: &[Reservation]) -> ReservationDiff {
+    let previous = by_code(previous);
+    let current = by_code(current);
+    let mut diff = ReservationDiff::default();
+
+    let mut codes: Vec<&str> = previous.keys().chain(current.keys()).copied().collect();
+    codes.sort_unstable();
+    codes.dedup();
+
+    for code in codes {
+        let (kind, listing_name, changes) = match (previous.get(code), current.get(code)) {
+            (None, Some(current)) => (ChangeKind::New, &current.listing_name, Vec::new()),
+            (Some(previous), None) => (ChangeKind::Disappeared, &previous.listing_name, Vec::new()),
+            (Some(previous), Some(current)) => {
+                let changes = field_changes(previous, current);
+                if changes.is_empty() {
+                    continue;
+                }
+                let kind = if is_cancelled(&current.status) && !is_cancelled(&previous.status) {
+                    ChangeKind::Cancelled
+                } else {
+                    ChangeKind::Modified
+                };
+                (kind, &current.listing_name, changes)
+            }
+            (None, None) => continue,
+        };
+        match kind {
+            ChangeKind::New => diff.new += 1,
+            ChangeKind::Cancelled => diff.cancelled += 1,
+            ChangeKind::Modified => diff.modified += 1,
+            ChangeKind::Disappeared => diff.disappeared += 1,
+        }
+        diff.reservations.push(ReservationChange {
+            confirmation_code: code.to_string(),
+            kind,
+            listing_name: listing_name.clone(),
+            changes,
+        });
+    }
+    diff
+}
@@ -1,201 +1,79 @@
-use super::excel::write_to_excel_file;
-use crate::config::globals::CONFIG;
-use crate::models::reservation::{Reservation, ReservationTable};
-use aws_credential_types::Credentials;
-use aws_sdk_ssm::{config::Region, Client as ssm_client, Config};
-use dotenv::dotenv;
+use super::commission::{decimal, CommissionRule, CommissionRules, RuleScope};
+use super::dates::validate_range;
+use super::excel::open_xlsx;
+use super::mapping::{validate, Issue};
+use super::money::{cents_to_amounts, parse_money, to_cents, Rounding};
+use super::registry::ListingRegistry;
+use super::summary::ListingCounts;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::models::api::{ReservationsPage, SchemaReport};
+use crate::models::query::ReservationQuery;
+use crate::models::reservation::{Reservation, ReservationTable, RESERVATION_COLUMNS};
+use crate::sources::ReservationSource;
 use polars::prelude::*;
-use regex::Regex;
-use reqwest::blocking::Client as req_client;
-use reqwest::header;
-use reqwest::StatusCode;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::env;
-use std::process;
-use tokio::runtime::Runtime;
-
-pub fn get_data(min_date: &str) -> Result<(String, Vec<Reservation>), reqwest::Error> {
-    dotenv().ok();
-    // Environment variables
-    let ssm_prefix = env::var("SSM_PREFIX").expect("SSM_PREFIX is not set");
-
-    // Hardcoded keys
-    let access_key_id = "";
-    let secret_access_key = "";
-
-    // Import variables from AWS SSM
-    let credentials = Credentials::from_keys(access_key_id, secret_access_key, None);
-    let config = Config::builder()
-        .credentials_provider(credentials)
-        .region(Region::new("us-east-1"))
-        .behavior_version_latest()
-        .build();
-    let client = ssm_client::from_conf(config);
-    let response = Runtime::new().unwrap().block_on(async {
-        client
-            .get_parameters_by_path()
-            .path(&ssm_prefix)
-            .send()
-            .await
-    });
-
-    let mut _params: HashMap<String, String> = HashMap::new();
-    for param in response.unwrap().parameters.unwrap() {
-        _params.insert(
-            param.name.unwrap().to_string(),
-            param.value.unwrap().to_string(),
-        );
-    }
+use std::path::PathBuf;
 
-    let _enable: String = _params
-        .get(format!("{}/{}", ssm_prefix, "ENABLE").as_str())
-        .unwrap()
-        .to_string();
-    if _enable != "true" {
-        println!("The service is disabled");
-        process::exit(1);
-    }
+/// Every reservation matching `query`, with the unknown and missing fields
+/// of the payload.
+pub fn fetch_reservations(
+    source: &mut dyn ReservationSource,
+    query: &ReservationQuery,
+) -> Result<(Vec<Reservation>, SchemaReport)> {
+    validate_range(&query.date_min, query.date_max.as_deref())?;
 
-    let _aat: String = _params
-        .get(format!("{}/{}", ssm_prefix, "_AAT").as_str())
-        .unwrap()
-        .to_string();
-    let airbnb_api_key: String = _params
-        .get(format!("{}/{}", ssm_prefix, "AIRBNB_API_KEY").as_str())
-        .unwrap()
-        .to_string();
-
-    // Cookies
-    let cookies = format!("country=PE; _aat={}", _aat);
-
-    // Headers
-    let mut headers = header::HeaderMap::new();
-    headers.insert("Accept", "*/*".parse().unwrap());
-    headers.insert("Connection", "keep-alive".parse().unwrap());
-    headers.insert("Content-Type", "application/json".parse().unwrap());
-    headers.insert("Host", "www.airbnb.com".parse().unwrap());
-    headers.insert(
-        "Referer",
-        "https://www.airbnb.com/hosting/reservations"
-            .parse()
-            .unwrap(),
-    );
-    headers.insert("x-airbnb-api-key", airbnb_api_key.parse().unwrap());
-
-    // Query parameters
-    let mut query_params: HashMap<String, String> = HashMap::new();
-    query_params.insert("locale".to_string(), "en".to_string());
-    query_params.insert("currency".to_string(), "PEN".to_string());
-    query_params.insert("_format".to_string(), "for_remy".to_string());
-    query_params.insert("_limit".to_string(), "40".to_string());
-    query_params.insert(
-        "collection_strategy".to_string(),
-        "for_reservations_list".to_string(),
-    );
-    query_params.insert("date_min".to_string(), min_date.to_string());
-    query_params.insert("status".to_string(), "accepted,request".to_string());
-
-    let mut _offset: i64 = 0;
-    let delta: i64 = 40;
+    let mut _offset: u32 = 0;
+    let delta: u32 = query.page_size.max(1);
     let mut total_pages: i64 = 0;
     let mut page: i64 = 1;
     let mut reservations: Vec<Reservation> = Vec::new();
+    let mut schema = SchemaReport::default();
 
-    let req_client = req_client::new();
     loop {
-        let offset_value: String = _offset.to_string();
-        query_params.insert("_offset".to_string(), offset_value);
-        // println!("{:?}", query_params);
-
-        let response = req_client
-            .get("https://www.airbnb.com/api/v2/reservations")
-            .headers(headers.clone())
-            .query(&query_params)
-            .header(reqwest::header::COOKIE, cookies.clone())
-            .send()
-            .expect("Failed to send request");
-
-        match response.status() {
-            StatusCode::OK => (),
-            StatusCode::CREATED => (),
-            StatusCode::ACCEPTED => (),
-            StatusCode::UNAUTHORIZED => {
-                println!("Status code: {}", response.status());
-                // TODO: Update aat in cookies
-                process::exit(1);
-            }
-            _ => {
-                println!("Status code: {}", response.status());
-                process::exit(1);
-            }
-        }
+        let query_params: HashMap<String, String> = query.to_params(_offset);
 
-        let data: Value = response.json()?;
-        if let Some(metadata) = data["metadata"].as_object() {
-            if page == 1 {
-                let total_records: i64 = metadata["total_count"].as_i64().unwrap_or(0);
-                total_pages = metadata["page_count"].as_i64().unwrap_or(0);
-                println!("Total records: {}", total_records);
-                println!("Total pages: {}", total_pages);
-            }
+        let data: Value = source.fetch_page(&query_params)?;
+        let data: ReservationsPage = serde_json::from_value(data)
+            .map_err(|e| Error::Schema(format!("page {}: {}", page, e)))?;
+        schema.record(&data);
+        if page == 1 {
+            let metadata = data.metadata.unwrap_or_default();
+            let total_records = metadata
+                .total_count
+                .and_then(|count| count.number())
+                .unwrap_or_default() as i64;
+            total_pages = metadata
+                .page_count
+                .and_then(|count| count.number())
+                .unwrap_or_default() as i64;
+            println!("Total records: {}", total_records);
+            println!("Total pages: {}", total_pages);
         }
 
-        if let Some(records) = data["reservations"].as_array() {
-            for record in records {
-                let reservation = Reservation {
-                    confirmation_code: record["confirmation_code"]
-                        .as_str()
-                        .unwrap_or("")
-                        .to_string(),
-                    status: record["user_facing_status_localized"]
-                        .as_str()
-                        .unwrap_or("")
-                        .to_string(),
-                    guest_user_full_name: record["guest_user"]["full_name"]
-                        .as_str()
-                        .unwrap_or("")
-                        .to_string(),
-                    guest_user_phone: record["guest_user"]["phone"]
-                        .as_str()
-                        .unwrap_or("")
-                        .to_string(),
-                    guest_details_number_of_adults: record["guest_details"]["number_of_adults"]
-                        .as_f64()
-                        .unwrap(),
-                    guest_details_number_of_children: record["guest_details"]["number_of_children"]
-                        .as_f64()
-                        .unwrap(),
-                    guest_details_number_of_infants: record["guest_details"]["number_of_infants"]
-                        .as_f64()
-                        .unwrap(),
-                    start_date: record["start_date"].as_str().unwrap_or("").to_string(),
-                    end_date: record["end_date"].as_str().unwrap_or("").to_string(),
-                    nights: record["nights"].as_f64().unwrap(),
-                    booked_date: record["booked_date"].as_str().unwrap_or("").to_string(),
-                    listing_name: record["listing_name"].as_str().unwrap_or("").to_string(),
-                    earnings: record["earnings"].as_str().unwrap_or("").to_string(),
-                };
-                reservations.push(reservation);
+        for record in data.reservations {
+            let reservation = Reservation::from(record);
+            if !reservation.invalid_dates.is_empty() {
+                println!(
+                    "Reservation {} has invalid dates: {}",
+                    reservation.confirmation_code,
+                    reservation.invalid_dates.join(", ")
+                );
             }
+            reservations.push(reservation);
         }
 
         println!("Page: {}", page);
-        if page == total_pages {
+        if page >= total_pages {
             break;
         }
         page += 1;
         _offset += delta;
     }
 
-    let filename: String = format!(
-        "{}_{}.xlsx",
-        CONFIG.output_file_prefix_1.as_str(),
-        CONFIG.datetime_suffix.as_str()
-    );
-    write_to_excel_file(&filename, &reservations).expect("Failed to write to file");
-
-    Ok((filename.to_string(), reservations))
+    Ok((reservations, schema))
 }
 
 fn row_to_column_structure(data: Vec<Reservation>) -> ReservationTable {
@@ -203,8 +81,11 @@ fn row_to_column_structure(data: Vec<Reservation>) -> ReservationTable {
     for record in data {
         table.confirmation_code.push(record.confirmation_code);
         table.status.push(record.status);
+        table.thread_id.push(record.thread_id);
+        table.guest_user_id.push(record.guest_user_id);
         table.guest_user_full_name.push(record.guest_user_full_name);
         table.guest_user_phone.push(record.guest_user_phone);
+        table.guest_user_country.push(record.guest_user_country);
         table
             .guest_details_number_of_adults
             .push(record.guest_details_number_of_adults);
@@ -216,103 +97,329 @@ fn row_to_column_structure(data: Vec<Reservation>) -> ReservationTable {
             .push(record.guest_details_number_of_infants);
         table.start_date.push(record.start_date);
         table.end_date.push(record.end_date);
+        table.check_in_time.push(record.check_in_time);
+        table.check_out_time.push(record.check_out_time);
         table.nights.push(record.nights);
         table.booked_date.push(record.booked_date);
+        table.listing_id.push(record.listing_id);
         table.listing_name.push(record.listing_name);
         table.earnings.push(record.earnings);
+        let payout = record.payout_breakdown;
+        table
+            .payout_breakdown_accommodation
+            .push(payout.accommodation);
+        table
+            .payout_breakdown_cleaning_fee
+            .push(payout.cleaning_fee);
+        table.payout_breakdown_service_fee.push(payout.service_fee);
+        table.payout_breakdown_taxes.push(payout.taxes);
+        table.payout_breakdown_total.push(payout.total);
+        table.cancellation_policy.push(record.cancellation_policy);
+        table
+            .invalid_dates
+            .push((!record.invalid_dates.is_empty()).then(|| record.invalid_dates.join(", ")));
     }
-    return table;
+    table
 }
 
 fn column_to_series_structure(table: ReservationTable) -> Vec<Series> {
-    let confirmation_code_series = Series::new("confirmation_code", table.confirmation_code);
-    let status_series = Series::new("status", table.status);
-    let guest_user_full_name_series =
-        Series::new("guest_user_full_name", table.guest_user_full_name);
-    let guest_user_phone_series = Series::new("guest_user_phone", table.guest_user_phone);
-    let guest_details_number_of_adults_series = Series::new(
-        "guest_details_number_of_adults",
-        table.guest_details_number_of_adults,
-    );
-    let guest_details_number_of_children_series = Series::new(
-        "guest_details_number_of_children",
-        table.guest_details_number_of_children,
-    );
-    let guest_details_number_of_infants_series = Series::new(
-        "guest_details_number_of_infants",
-        table.guest_details_number_of_infants,
-    );
-    let start_date_series = Series::new("start_date", table.start_date);
-    let end_date_series = Series::new("end_date", table.end_date);
-    let nights_series = Series::new("nights", table.nights);
-    let booked_date_series = Series::new("booked_date", table.booked_date);
-    let listing_name_series = Series::new("listing_name", table.listing_name);
-    let earnings_series = Series::new("earnings", table.earnings);
-
     vec![
-        confirmation_code_series,
-        status_series,
-        guest_user_full_name_series,
-        guest_user_phone_series,
-        guest_details_number_of_adults_series,
-        guest_details_number_of_children_series,
-        guest_details_number_of_infants_series,
-        start_date_series,
-        end_date_series,
-        nights_series,
-        booked_date_series,
-        listing_name_series,
-        earnings_series,
+        Series::new("confirmation_code", table.confirmation_code),
+        Series::new("status", table.status),
+        Series::new("thread_id", table.thread_id),
+        Series::new("guest_user_id", table.guest_user_id),
+        Series::new("guest_user_full_name", table.guest_user_full_name),
+        Series::new("guest_user_phone", table.guest_user_phone),
+        Series::new("guest_user_country", table.guest_user_country),
+        Series::new(
+            "guest_details_number_of_adults",
+            table.guest_details_number_of_adults,
+        ),
+        Series::new(
+            "guest_details_number_of_children",
+            table.guest_details_number_of_children,
+        ),
+        Series::new(
+            "guest_details_number_of_infants",
+            table.guest_details_number_of_infants,
+        ),
+        Series::new("start_date", table.start_date),
+        Series::new("end_date", table.end_date),
+        Series::new("check_in_time", table.check_in_time),
+        Series::new("check_out_time", table.check_out_time),
+        Series::new("nights", table.nights),
+        Series::new("booked_date", table.booked_date),
+        Series::new("listing_id", table.listing_id),
+        Series::new("listing_name", table.listing_name),
+        Series::new("earnings", table.earnings),
+        Series::new(
+            "payout_breakdown_accommodation",
+            table.payout_breakdown_accommodation,
+        ),
+        Series::new(
+            "payout_breakdown_cleaning_fee",
+            table.payout_breakdown_cleaning_fee,
+        ),
+        Series::new(
+            "payout_breakdown_service_fee",
+            table.payout_breakdown_service_fee,
+        ),
+        Series::new("payout_breakdown_taxes", table.payout_breakdown_taxes),
+        Series::new("payout_breakdown_total", table.payout_breakdown_total),
+        Series::new("cancellation_policy", table.cancellation_policy),
+        Series::new("invalid_dates", table.invalid_dates),
     ]
 }
 
-pub fn get_dataframe(data: Vec<Reservation>) -> Result<DataFrame, PolarsError> {
+/// Keeps the reservation `columns` in the given order, followed by every
+/// column the pipeline added (amounts, owners, commissions). Columns missing
+/// from `df`, e.g. in JSON written by an older version, are skipped.
+pub fn select_columns(df: DataFrame, columns: &[String]) -> Result<DataFrame> {
+    let present: Vec<&str> = df.get_column_names();
+    let selected: Vec<&str> = columns
+        .iter()
+        .map(String::as_str)
+        .filter(|name| present.contains(name))
+        .chain(
+            present
+                .iter()
+                .copied()
+                .filter(|name| !RESERVATION_COLUMNS.iter().any(|(column, _)| column == name)),
+        )
+        .collect();
+    Ok(df.select(selected)?)
+}
+
+/// Date columns of the reservations, read back as text from JSON.
+const DATE_COLUMNS: [&str; 3] = ["start_date", "end_date", "booked_date"];
+
+/// Turns the date columns of a frame read from JSON back into `Date` columns.
+pub fn restore_dates(df: DataFrame) -> Result<DataFrame> {
+    let dates: Vec<Expr> = DATE_COLUMNS
+        .iter()
+        .filter(|name| {
+            df.column(name)
+                .is_ok_and(|column| column.dtype() == &DataType::String)
+        })
+        .map(|name| col(name).cast(DataType::Date))
+        .collect();
+    Ok(df.lazy().with_columns(dates).collect()?)
+}
+
+pub fn get_dataframe(data: Vec<Reservation>) -> Result<DataFrame> {
     let table: ReservationTable = row_to_column_structure(data);
     let df = DataFrame::new(column_to_series_structure(table))?;
     Ok(df)
 }
 
-pub fn process_data(df: DataFrame) -> Result<DataFrame, PolarsError> {
-    let pattern: Regex = Regex::new(r"([^\d,.]+)?([\d,]+\.\d+)").unwrap();
-
-    let mut currencies: Vec<&str> = Vec::new();
-    let mut amounts: Vec<f64> = Vec::new();
-
-    for opt_s in df.column("earnings").into_iter() {
-        let s = opt_s.str().unwrap();
-        for s in s {
-            let caps = pattern.captures(s.unwrap()).unwrap();
-            currencies.push(caps.get(1).unwrap().as_str());
-            amounts.push(
-                caps.get(2)
-                    .unwrap()
-                    .as_str()
-                    .replace(",", "")
-                    .parse::<f64>()
-                    .unwrap_or(0.0),
-            );
+/// Splits `earnings` into the ISO `currency`, the exact `amount_cents` and
+/// the `amount`; amounts without a currency symbol are in `default_currency`.
+/// Rows that do not parse keep them empty and are marked with the reason in
+/// an `earnings_error` column, added when any row fails.
+pub fn process_data(df: DataFrame, default_currency: &str) -> Result<DataFrame> {
+    let mut currencies: Vec<Option<String>> = Vec::new();
+    let mut cents: Vec<Option<i64>> = Vec::new();
+    let mut errors: Vec<Option<String>> = Vec::new();
+
+    let earnings = df.column("earnings")?.str()?;
+    for value in earnings {
+        // Airbnb amounts carry at most two decimals, so the rounding never applies
+        match parse_money(value.unwrap_or_default(), default_currency)
+            .and_then(|money| Ok((money.currency, to_cents(money.amount, Rounding::HalfUp)?)))
+        {
+            Ok((currency, amount_cents)) => {
+                currencies.push(Some(currency));
+                cents.push(Some(amount_cents));
+                errors.push(None);
+            }
+            Err(e) => {
+                currencies.push(None);
+                cents.push(None);
+                errors.push(Some(e.to_string()));
+            }
         }
     }
 
-    let currency_series = Series::new("currency", currencies);
-    let amount_series = Series::new("amount", amounts);
+    let mut columns = vec![
+        Series::new("currency", currencies),
+        Series::new("amount", cents_to_amounts(&cents)),
+        Series::new("amount_cents", cents),
+    ];
+    if errors.iter().any(Option::is_some) {
+        columns.push(Series::new("earnings_error", errors));
+    }
 
-    let _df = df
-        .hstack(&[currency_series, amount_series])
-        .expect("Failed to hstack");
+    let _df = df.hstack(&columns)?;
 
     Ok(_df)
 }
 
-pub fn get_summary(df: DataFrame) -> Result<DataFrame, PolarsError> {
-    let _df = df
+fn get_path(filename: &str) -> Result<String> {
+    let cwd = env::current_dir().map_err(|e| Error::io("Failed to get current directory", e))?;
+    let _filename: PathBuf = cwd.join(filename);
+    Ok(_filename.to_string_lossy().to_string())
+}
+
+fn require_columns(df: &DataFrame, sheet: &str, columns: &[&str]) -> Result<()> {
+    for column in columns {
+        if df.column(column).is_err() {
+            return Err(Error::mapping_sheet(
+                sheet,
+                format!("missing column {}", column),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Issues of the listing and owner sheets of the base workbook with the
+/// listings of `df`.
+pub fn get_mapping_issues(config: &Config, df: &DataFrame) -> Result<Vec<Issue>> {
+    let base_xlsx = get_path(config.base_xlsx.as_str())?;
+    let listings = open_xlsx(&base_xlsx, &config.first_sheet)?;
+    require_columns(&listings, &config.first_sheet, &["ANUNCIO", "PROPIETARIO"])?;
+    let owners = open_xlsx(&base_xlsx, &config.second_sheet)?;
+    require_columns(&owners, &config.second_sheet, &["PROPIETARIO"])?;
+    let registry = listing_registry(config, &listings)?;
+    validate(df, &registry, &listings, &owners)
+}
+
+/// Listings per owner and zone in the first sheet of the base workbook.
+pub fn get_listing_counts(config: &Config) -> Result<ListingCounts> {
+    let base_xlsx = get_path(config.base_xlsx.as_str())?;
+    let listings = open_xlsx(&base_xlsx, &config.first_sheet)?;
+    require_columns(&listings, &config.first_sheet, &["ANUNCIO", "PROPIETARIO"])?;
+    ListingCounts::from_registry(&listings, &config.zone_column)
+}
+
+fn listing_registry(config: &Config, listings: &DataFrame) -> Result<ListingRegistry> {
+    ListingRegistry::from_dataframe(listings, config.listing_match_threshold)
+        .map_err(|message| Error::mapping_sheet(&config.first_sheet, message))
+}
+
+pub fn get_consolidated_data(config: &Config, processed_df: DataFrame) -> Result<DataFrame> {
+    // Auxiliar dataframes
+    let mut hm_dataframes: HashMap<&str, DataFrame> = HashMap::new();
+    let worksheets = [
+        config.first_sheet.as_str(),
+        config.second_sheet.as_str(),
+        config.third_sheet.as_str(),
+    ];
+    let base_xlsx = get_path(config.base_xlsx.as_str())?;
+    for sheet in worksheets {
+        let excel_df: DataFrame = open_xlsx(&base_xlsx, sheet)?;
+        hm_dataframes.insert(sheet, excel_df);
+    }
+    require_columns(
+        &hm_dataframes[config.first_sheet.as_str()],
+        &config.first_sheet,
+        &["ANUNCIO", "PROPIETARIO"],
+    )?;
+    require_columns(
+        &hm_dataframes[config.second_sheet.as_str()],
+        &config.second_sheet,
+        &["PROPIETARIO", "Comision"],
+    )?;
+
+    let rules = commission_rules(config, &base_xlsx, &hm_dataframes)?;
+    let registry = listing_registry(config, &hm_dataframes[config.first_sheet.as_str()])?;
+
+    let aux_df = hm_dataframes[config.first_sheet.as_str()]
+        .clone()
         .lazy()
-        .group_by([col("listing"), col("currency")])
-        .agg([col("amount").sum().alias("total")])
-        .sort(
-            ["total"],
-            SortMultipleOptions::new().with_order_descending(true),
+        .with_row_index("registry_row", None)
+        .join(
+            // One row per owner, dated rates are in the commission rules
+            hm_dataframes[config.second_sheet.as_str()]
+                .clone()
+                .lazy()
+                .select([col("*").exclude(["Comision", "agreement", "valid_from", "valid_to"])])
+                .unique_stable(
+                    Some(vec!["PROPIETARIO".to_string()]),
+                    UniqueKeepStrategy::First,
+                ),
+            [col("PROPIETARIO")],
+            [col("PROPIETARIO")],
+            JoinArgs::new(JoinType::Left),
         )
         .collect()?;
-    Ok(_df)
+
+    // Each reservation joins the listing it matches by ID or name
+    let matches = registry.resolve(&processed_df)?;
+    let rows: Vec<Option<IdxSize>> = matches
+        .iter()
+        .map(|found| found.map(|found| found.row as IdxSize))
+        .collect();
+    let kinds: Vec<Option<&str>> = matches
+        .iter()
+        .map(|found| found.map(|found| found.kind.as_str()))
+        .collect();
+    let scores: Vec<Option<f64>> = matches
+        .iter()
+        .map(|found| found.map(|found| found.score))
+        .collect();
+    let matched_df = processed_df.hstack(&[
+        Series::new("registry_row", rows),
+        Series::new("listing_match", kinds),
+        Series::new("listing_match_score", scores),
+    ])?;
+
+    // Consolidated df
+    let cons_df = matched_df
+        .lazy()
+        .join(
+            aux_df.lazy(),
+            [col("registry_row")],
+            [col("registry_row")],
+            JoinArgs::new(JoinType::Left),
+        )
+        .select([col("*").exclude(["registry_row"])])
+        .collect()?;
+
+    rules.apply(
+        cons_df,
+        &config.zone_column,
+        config.commission_date,
+        config.commission_rounding,
+    )
+}
+
+/// The rules of `COMMISSION_RULES_SHEET`, then the (possibly dated)
+/// `Comision` of the owners of the second sheet and the base commission of
+/// the third one, each only for the owners and default the rules sheet leaves
+/// out.
+fn commission_rules(
+    config: &Config,
+    base_xlsx: &str,
+    sheets: &HashMap<&str, DataFrame>,
+) -> Result<CommissionRules> {
+    let mut rules = match &config.commission_rules_sheet {
+        Some(sheet) => CommissionRules::from_dataframe(&open_xlsx(base_xlsx, sheet)?)
+            .map_err(|message| Error::mapping_sheet(sheet, message))?,
+        None => CommissionRules::default(),
+    };
+
+    rules
+        .push_owner_rates(&sheets[config.second_sheet.as_str()])
+        .map_err(|message| Error::mapping_sheet(&config.second_sheet, message))?;
+
+    let cell = sheets[config.third_sheet.as_str()]
+        .get(0)
+        .and_then(|row| row.first().map(|cell| cell.to_string()))
+        .ok_or_else(|| Error::mapping_sheet(&config.third_sheet, "missing the base commission"))?;
+    let base_comission = decimal(cell.trim_matches('"')).ok_or_else(|| {
+        Error::mapping_sheet(
+            &config.third_sheet,
+            format!("the base commission {} is not a number", cell),
+        )
+    })?;
+    if !rules.covers(RuleScope::Default, "") {
+        rules.push(CommissionRule::new(
+            "base",
+            RuleScope::Default,
+            "",
+            base_comission,
+        ));
+    }
+    Ok(rules)
 }
@@ -1,145 +1,319 @@
-use crate::models::reservation::Reservation;
+use super::dates::from_epoch_days;
+use super::diff::{FieldChange, ReservationDiff};
+use super::mapping::Issue;
+use super::statement::Statement;
+use super::summary::Summary;
+use crate::error::{Error, Result};
+use crate::models::reservation::RESERVATION_COLUMNS;
 use calamine::{open_workbook_auto, Reader};
+use chrono::{Datelike, NaiveDate};
 use polars::datatypes as dtype;
 use polars::prelude::*;
+use serde_json::Value;
 use xlsxwriter::format::Format;
+use xlsxwriter::worksheet::DateTime;
 use xlsxwriter::{Workbook, Worksheet, XlsxError};
 
-pub fn write_to_excel_file(
-    filename: &str,
-    reservations: &Vec<Reservation>,
-) -> Result<(), XlsxError> {
-    let workbook: Workbook = Workbook::new(filename)?;
-    let mut worksheet: Worksheet = workbook.add_worksheet(None)?;
+type XlsxResult = std::result::Result<(), XlsxError>;
 
-    // Write headers
-    worksheet.write_string(0, 0, "Confirmation code", None)?;
-    worksheet.write_string(0, 1, "Status", None)?;
-    worksheet.write_string(0, 2, "Guest name", None)?;
-    worksheet.write_string(0, 3, "Contact", None)?;
-    worksheet.write_string(0, 4, "# of adults", None)?;
-    worksheet.write_string(0, 5, "# of children", None)?;
-    worksheet.write_string(0, 6, "# of infants", None)?;
-    worksheet.write_string(0, 7, "Start date", None)?;
-    worksheet.write_string(0, 8, "End date", None)?;
-    worksheet.write_string(0, 9, "# of nights", None)?;
-    worksheet.write_string(0, 10, "Booked", None)?;
-    worksheet.write_string(0, 11, "Listing", None)?;
-    worksheet.write_string(0, 12, "Earnings", None)?;
-
-    // Iterate over the reservations and write data
-    for (index, reservation) in reservations.iter().enumerate() {
-        let row = (index + 1) as u32; // Start writing from the second row
-        worksheet.write_string(row, 0, &reservation.confirmation_code, None)?;
-        worksheet.write_string(row, 1, &reservation.status, None)?;
-        worksheet.write_string(row, 2, &reservation.guest_user_full_name, None)?;
-        worksheet.write_string(row, 3, &reservation.guest_user_phone, None)?;
-        worksheet.write_number(
-            row,
-            4,
-            reservation.guest_details_number_of_adults,
-            Some(&Format::new().set_num_format("#,##0")),
-        )?;
-        worksheet.write_number(
-            row,
-            5,
-            reservation.guest_details_number_of_children,
-            Some(&Format::new().set_num_format("#,##0")),
-        )?;
-        worksheet.write_number(
-            row,
-            6,
-            reservation.guest_details_number_of_infants,
-            Some(&Format::new().set_num_format("#,##0")),
-        )?;
-        worksheet.write_string(row, 7, &reservation.start_date, None)?;
-        worksheet.write_string(row, 8, &reservation.end_date, None)?;
-        worksheet.write_number(
+const EXCEL_DATE_FORMAT: &str = "yyyy-mm-dd";
+
+/// Headers of the columns the pipeline adds to the reservations.
+const DERIVED_HEADERS: [(&str, &str); 28] = [
+    ("currency", "Currency"),
+    ("amount", "Amount"),
+    ("amount_cents", "Amount (cents)"),
+    ("earnings_error", "Earnings error"),
+    ("listing_match", "Listing match"),
+    ("listing_match_score", "Match score"),
+    ("ANUNCIO", "Listing"),
+    ("PROPIETARIO", "Owner"),
+    ("Comision", "Comission (%)"),
+    ("commission_rule", "Comission rule"),
+    ("commission_agreement", "Agreement"),
+    ("commission_earnings", "Comission (#)"),
+    ("commission_cents", "Comission (cents)"),
+    ("commission_error", "Comission error"),
+    ("reporting_currency", "Reporting currency"),
+    ("fx_rate", "Exchange rate"),
+    ("converted_amount", "Converted amount"),
+    ("converted_amount_cents", "Converted amount (cents)"),
+    ("converted_commission_earnings", "Converted comission (#)"),
+    ("converted_commission_cents", "Converted comission (cents)"),
+    ("fx_error", "Exchange rate error"),
+    ("month", "Month"),
+    ("reservations", "Reservations"),
+    ("listings", "Listings"),
+    ("gross", "Gross earnings"),
+    ("commission", "Comission"),
+    ("net", "Net to owner"),
+    ("occupancy", "Occupancy"),
+];
+
+/// Columns written with two decimals.
+const MONEY_COLUMNS: [&str; 7] = [
+    "amount",
+    "commission_earnings",
+    "converted_amount",
+    "converted_commission_earnings",
+    "gross",
+    "commission",
+    "net",
+];
+
+/// Columns written as percentages.
+const PERCENT_COLUMNS: [&str; 2] = ["listing_match_score", "occupancy"];
+
+/// Workbook header of a frame column; columns of the base workbook without a
+/// known header keep their name.
+fn header(name: &str) -> &str {
+    RESERVATION_COLUMNS
+        .iter()
+        .chain(DERIVED_HEADERS.iter())
+        .find(|(column, _)| *column == name)
+        .map_or(name, |(_, header)| header)
+}
+
+fn write_error(filename: &str, source: XlsxError) -> Error {
+    Error::ExcelWrite {
+        path: filename.to_string(),
+        source,
+    }
+}
+
+/// Real Excel date cell; missing dates stay blank.
+fn write_date(
+    worksheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    date: Option<NaiveDate>,
+) -> XlsxResult {
+    match date {
+        Some(date) => worksheet.write_datetime(
             row,
-            9,
-            reservation.nights,
-            Some(&Format::new().set_num_format("#,##0")),
-        )?;
-        worksheet.write_string(row, 10, &reservation.booked_date, None)?;
-        worksheet.write_string(row, 11, &reservation.listing_name, None)?;
-        worksheet.write_string(row, 12, &reservation.earnings, None)?;
+            col,
+            &DateTime::date(date.year() as i16, date.month() as i8, date.day() as i8),
+            Some(Format::new().set_num_format(EXCEL_DATE_FORMAT)),
+        ),
+        None => Ok(()),
     }
+}
 
-    workbook.close()
+pub fn write_to_excel_file_refac(filename: &str, data: DataFrame) -> Result<()> {
+    write_workbook(filename, &data, &[], None).map_err(|e| write_error(filename, e))
+}
+
+/// The reservations on the first sheet, each summary on its own sheet and
+/// the base workbook `issues` on an `Issues` sheet.
+pub fn write_report_to_excel_file(
+    filename: &str,
+    data: DataFrame,
+    summaries: &[Summary],
+    issues: &[Issue],
+) -> Result<()> {
+    write_workbook(filename, &data, summaries, Some(issues)).map_err(|e| write_error(filename, e))
 }
 
-pub fn write_to_excel_file_refac(filename: &str, data: DataFrame) -> Result<(), XlsxError> {
+fn write_workbook(
+    filename: &str,
+    data: &DataFrame,
+    summaries: &[Summary],
+    issues: Option<&[Issue]>,
+) -> XlsxResult {
     let workbook: Workbook = Workbook::new(filename)?;
     let mut worksheet: Worksheet = workbook.add_worksheet(None)?;
+    write_dataframe(&mut worksheet, data)?;
+    for summary in summaries {
+        let mut worksheet: Worksheet = workbook.add_worksheet(Some(&summary.sheet))?;
+        write_dataframe(&mut worksheet, &summary.df)?;
+    }
+    if let Some(issues) = issues {
+        let mut worksheet: Worksheet = workbook.add_worksheet(Some("Issues"))?;
+        write_issues(&mut worksheet, issues)?;
+    }
+    workbook.close()
+}
 
+fn write_issues(worksheet: &mut Worksheet, issues: &[Issue]) -> XlsxResult {
     // Write headers
-    worksheet.write_string(0, 0, "Confirmation code", None)?;
-    worksheet.write_string(0, 1, "Status", None)?;
-    worksheet.write_string(0, 2, "Guest name", None)?;
-    worksheet.write_string(0, 3, "Contact", None)?;
-    worksheet.write_string(0, 4, "# of adults", None)?;
-    worksheet.write_string(0, 5, "# of children", None)?;
-    worksheet.write_string(0, 6, "# of infants", None)?;
-    worksheet.write_string(0, 7, "Start date", None)?;
-    worksheet.write_string(0, 8, "End date", None)?;
-    worksheet.write_string(0, 9, "# of nights", None)?;
-    worksheet.write_string(0, 10, "Booked", None)?;
-    worksheet.write_string(0, 11, "Listing", None)?;
-    worksheet.write_string(0, 12, "Earnings", None)?;
-    worksheet.write_string(0, 13, "Currency", None)?;
-    worksheet.write_string(0, 14, "Amount", None)?;
-    worksheet.write_string(0, 15, "Owner", None)?;
-    worksheet.write_string(0, 16, "Zone", None)?;
-    worksheet.write_string(0, 17, "Comission (%)", None)?;
-    worksheet.write_string(0, 18, "Comission (#)", None)?;
+    worksheet.write_string(0, 0, "Issue", None)?;
+    worksheet.write_string(0, 1, "Key", None)?;
+    worksheet.write_string(0, 2, "Detail", None)?;
+
+    for (row, issue) in issues.iter().enumerate() {
+        let row = row as u32 + 1;
+        worksheet.write_string(row, 0, issue.kind.label(), None)?;
+        worksheet.write_string(row, 1, &issue.key, None)?;
+        worksheet.write_string(row, 2, &issue.detail, None)?;
+    }
+    Ok(())
+}
+
+fn write_dataframe(worksheet: &mut Worksheet, data: &DataFrame) -> XlsxResult {
+    write_table(worksheet, data, 0)
+}
+
+/// `data` with its headers on `first_row`, its rows below them.
+fn write_table(worksheet: &mut Worksheet, data: &DataFrame, first_row: u32) -> XlsxResult {
+    // Write headers
+    let names = data.get_column_names();
+    for (col, name) in names.iter().enumerate() {
+        worksheet.write_string(first_row, col as u16, header(name), None)?;
+    }
 
+    let mut money_format = Format::new();
+    money_format.set_num_format("#,##0.00");
+    let mut percent_format = Format::new();
+    percent_format.set_num_format("0.0%");
+    let number_format = |name: &str| {
+        if MONEY_COLUMNS.contains(&name) {
+            Some(&money_format)
+        } else if PERCENT_COLUMNS.contains(&name) {
+            Some(&percent_format)
+        } else {
+            None
+        }
+    };
     let row_count = data.height() as u32;
     for i in 0..row_count {
-        let row = data.get(i as usize).unwrap();
-        row.iter().enumerate().for_each(|(col, cell)| {
+        let row = data.get(i as usize).unwrap_or_default();
+        for (col, cell) in row.iter().enumerate() {
             match cell.dtype() {
+                dtype::DataType::Null => Ok(()),
                 dtype::DataType::String => worksheet.write_string(
-                    i + 1,
+                    first_row + i + 1,
                     col as u16,
                     cell.to_string().trim_matches('"'),
                     None,
                 ),
-                dtype::DataType::Int64 => worksheet.write_number(
-                    i + 1,
+                dtype::DataType::Date => write_date(
+                    worksheet,
+                    first_row + i + 1,
                     col as u16,
-                    cell.to_string().parse::<f64>().unwrap(),
-                    None,
-                ),
-                dtype::DataType::Float64 => worksheet.write_number(
-                    i + 1,
-                    col as u16,
-                    cell.to_string().parse::<f64>().unwrap(),
-                    None,
+                    match cell {
+                        AnyValue::Date(days) => from_epoch_days(*days),
+                        _ => None,
+                    },
                 ),
+                dtype::DataType::Int64 | dtype::DataType::UInt32 | dtype::DataType::Float64 => {
+                    worksheet.write_number(
+                        first_row + i + 1,
+                        col as u16,
+                        cell.extract::<f64>().unwrap_or_default(),
+                        number_format(names[col]),
+                    )
+                }
                 _ => worksheet.write_string(
-                    i + 1,
+                    first_row + i + 1,
                     col as u16,
                     cell.to_string().trim_matches('"'),
                     None,
                 ),
+            }?;
+        }
+    }
+    Ok(())
+}
+
+/// An owner statement: a `Statement` sheet with the owner, zones and period
+/// on top of the totals per currency and per listing, and a `Reservations`
+/// sheet.
+pub fn write_statement_to_excel_file(filename: &str, statement: &Statement) -> Result<()> {
+    write_statement(filename, statement).map_err(|e| write_error(filename, e))
+}
+
+fn write_statement(filename: &str, statement: &Statement) -> XlsxResult {
+    let workbook: Workbook = Workbook::new(filename)?;
+    let mut worksheet: Worksheet = workbook.add_worksheet(Some("Statement"))?;
+
+    // Cover
+    worksheet.write_string(0, 0, "Owner statement", None)?;
+    worksheet.write_string(1, 0, "Owner", None)?;
+    worksheet.write_string(1, 1, &statement.owner, None)?;
+    worksheet.write_string(2, 0, "Zone", None)?;
+    worksheet.write_string(2, 1, &statement.zones.join(", "), None)?;
+    worksheet.write_string(3, 0, "From", None)?;
+    write_date(&mut worksheet, 3, 1, statement.period.map(|(from, _)| from))?;
+    worksheet.write_string(4, 0, "To", None)?;
+    write_date(&mut worksheet, 4, 1, statement.period.map(|(_, to)| to))?;
+
+    // Totals per currency, then per listing
+    write_table(&mut worksheet, &statement.totals, 6)?;
+    let listings_row = 6 + statement.totals.height() as u32 + 2;
+    write_table(&mut worksheet, &statement.listings, listings_row)?;
+
+    let mut worksheet: Worksheet = workbook.add_worksheet(Some("Reservations"))?;
+    write_dataframe(&mut worksheet, &statement.reservations)?;
+    workbook.close()
+}
+
+pub fn write_diff_to_excel_file(filename: &str, diff: &ReservationDiff) -> Result<()> {
+    write_diff(filename, diff).map_err(|e| write_error(filename, e))
+}
+
+fn diff_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn write_diff(filename: &str, diff: &ReservationDiff) -> XlsxResult {
+    let workbook: Workbook = Workbook::new(filename)?;
+    let mut worksheet: Worksheet = workbook.add_worksheet(Some("Changes"))?;
+
+    // Write headers
+    worksheet.write_string(0, 0, "Confirmation code", None)?;
+    worksheet.write_string(0, 1, "Change", None)?;
+    worksheet.write_string(0, 2, "Listing", None)?;
+    worksheet.write_string(0, 3, "Field", None)?;
+    worksheet.write_string(0, 4, "Before", None)?;
+    worksheet.write_string(0, 5, "After", None)?;
+
+    // One row per changed field, or a single row for new and disappeared reservations
+    let mut row: u32 = 1;
+    for change in &diff.reservations {
+        let fields: Vec<Option<&FieldChange>> = if change.changes.is_empty() {
+            vec![None]
+        } else {
+            change.changes.iter().map(Some).collect()
+        };
+        for field in fields {
+            worksheet.write_string(row, 0, &change.confirmation_code, None)?;
+            worksheet.write_string(row, 1, change.kind.label(), None)?;
+            worksheet.write_string(row, 2, &change.listing_name, None)?;
+            if let Some(field) = field {
+                worksheet.write_string(row, 3, &field.field, None)?;
+                worksheet.write_string(row, 4, &diff_value(&field.before), None)?;
+                worksheet.write_string(row, 5, &diff_value(&field.after), None)?;
             }
-            .expect("Failed to write cell")
-        });
+            row += 1;
+        }
     }
+
     workbook.close()
 }
 
-pub fn open_csv(filename: &str) -> Result<DataFrame, PolarsError> {
-    let df = CsvReader::from_path(filename)?.has_header(true).finish()?;
+/// Reads every column as text, like `open_xlsx`, so numbers keep all the
+/// digits of the file.
+pub fn open_csv(filename: &str) -> Result<DataFrame> {
+    let df = CsvReader::from_path(filename)?
+        .has_header(true)
+        .infer_schema(Some(0))
+        .finish()?;
     Ok(df)
 }
 
-pub fn open_xlsx(filename: &str, sheet_name: &str) -> Result<DataFrame, PolarsError> {
+pub fn open_xlsx(filename: &str, sheet_name: &str) -> Result<DataFrame> {
     // Open workbook and define sheet
-    let mut workbook = open_workbook_auto(filename).expect("Failed to open workbook");
+    let mut workbook = open_workbook_auto(filename).map_err(|e| Error::ExcelRead {
+        path: filename.to_string(),
+        message: e.to_string(),
+    })?;
     let worksheet = workbook
         .worksheet_range(sheet_name)
-        .expect("Failed to open worksheet");
+        .map_err(|e| Error::mapping_sheet(sheet_name, e.to_string()))?;
 
     // Schema
     let mut headers = Vec::new();
@@ -174,7 +348,8 @@ pub fn open_xlsx(filename: &str, sheet_name: &str) -> Result<DataFrame, PolarsEr
         series.push(s);
     }
 
-    let _df = DataFrame::new(series).expect("Failed to create dataframe");
+    let _df =
+        DataFrame::new(series).map_err(|e| Error::mapping_sheet(sheet_name, e.to_string()))?;
 
     Ok(_df)
 }
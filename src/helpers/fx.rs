@@ -0,0 +1,209 @@
+//! Conversion of the amounts into a single reporting currency.
+use super::columns::date_column;
+use super::dates::sheet_date;
+use super::excel::{open_csv, open_xlsx};
+use super::money::{apply_rate, cents_to_amounts, Rounding};
+use crate::error::{Error, Result};
+use chrono::NaiveDate;
+use polars::prelude::*;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Source of exchange rates.
+pub trait RateProvider {
+    /// Units of `to` one unit of `from` was worth on `date`.
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal>;
+}
+
+/// Rates from a local table with `date`, `from`, `to` and `rate` columns,
+/// where one `from` is worth `rate` units of `to` from `date` on.
+///
+/// The rate of a day is the last one on or before it, and a pair only listed
+/// the other way round uses the inverse rate.
+#[derive(Debug, Default)]
+pub struct RateTable {
+    rates: HashMap<(String, String), BTreeMap<NaiveDate, Decimal>>,
+}
+
+/// Text of a table cell, whatever type the reader inferred.
+fn cell_text(value: AnyValue) -> String {
+    match value {
+        AnyValue::Null => String::new(),
+        AnyValue::String(text) => text.to_string(),
+        other => other.to_string().trim_matches('"').to_string(),
+    }
+}
+
+impl RateTable {
+    /// Reads a `.csv` file, or the `sheet` of a workbook.
+    pub fn open(path: &Path, sheet: &str) -> Result<Self> {
+        let filename = path.to_string_lossy();
+        let df = if path.extension().is_some_and(|ext| ext == "csv") {
+            open_csv(&filename)?
+        } else {
+            open_xlsx(&filename, sheet)?
+        };
+        RateTable::from_dataframe(&df).map_err(|message| Error::ExcelRead {
+            path: filename.to_string(),
+            message,
+        })
+    }
+
+    fn from_dataframe(df: &DataFrame) -> std::result::Result<Self, String> {
+        let column = |name: &str| {
+            df.column(name)
+                .map_err(|_| format!("the rate table has no {} column", name))
+        };
+        let (dates, from, to, rates) = (
+            column("date")?,
+            column("from")?,
+            column("to")?,
+            column("rate")?,
+        );
+
+        let mut table = RateTable::default();
+        for row in 0..df.height() {
+            let cell = |series: &Series| series.get(row).map(cell_text).unwrap_or_default();
+            let date = cell(dates);
+            let date = sheet_date(&date)
+                .ok_or_else(|| format!("row {}: {:?} is not a date", row + 2, date))?;
+            let rate = cell(rates);
+            let rate = Decimal::from_str(&rate)
+                .or_else(|_| Decimal::from_scientific(&rate))
+                .ok()
+                .filter(|rate| *rate > Decimal::ZERO)
+                .ok_or_else(|| format!("row {}: {:?} is not a positive rate", row + 2, rate))?;
+            table.insert(&cell(from), &cell(to), date, rate);
+        }
+        Ok(table)
+    }
+
+    /// One `from` is worth `rate` units of `to` from `date` on.
+    pub fn insert(&mut self, from: &str, to: &str, date: NaiveDate, rate: Decimal) {
+        self.rates
+            .entry((from.trim().to_uppercase(), to.trim().to_uppercase()))
+            .or_default()
+            .insert(date, rate);
+    }
+
+    fn lookup(&self, from: &str, to: &str, date: NaiveDate) -> Option<Decimal> {
+        let key = (from.to_string(), to.to_string());
+        self.rates
+            .get(&key)
+            .and_then(|rates| rates.range(..=date).next_back())
+            .map(|(_, rate)| *rate)
+    }
+}
+
+impl RateProvider for RateTable {
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        if from == to {
+            return Ok(Decimal::ONE);
+        }
+        self.lookup(&from, &to, date)
+            .or_else(|| {
+                self.lookup(&to, &from, date)
+                    .and_then(|rate| Decimal::ONE.checked_div(rate))
+            })
+            .ok_or_else(|| {
+                Error::parse(
+                    "exchange rate",
+                    format!("no {} to {} rate on or before {}", from, to, date),
+                )
+            })
+    }
+}
+
+/// Adds the `reporting_currency`, the `fx_rate` and the converted amount
+/// (and commission, once computed) of every row, in cents and in currency
+/// units, keeping the original columns.
+///
+/// Each row uses the rate of its `start_date`, or of its `booked_date` when
+/// the start date is missing. Missing amounts and commissions stay missing,
+/// and rows without a rate are left unconverted and marked with the reason in
+/// an `fx_error` column, added when any row fails.
+pub fn convert(
+    df: DataFrame,
+    provider: &dyn RateProvider,
+    reporting_currency: &str,
+    rounding: Rounding,
+) -> Result<DataFrame> {
+    let currencies = df.column("currency")?.str()?;
+    let amounts = df.column("amount_cents")?.i64()?;
+    let commissions = match df.column("commission_cents") {
+        Ok(column) => Some(column.i64()?.clone()),
+        Err(_) => None,
+    };
+    let start_dates = date_column(&df, "start_date")?;
+    let booked_dates = date_column(&df, "booked_date")?;
+
+    let mut rates: Vec<Option<f64>> = Vec::with_capacity(df.height());
+    let mut amount_cents: Vec<Option<i64>> = Vec::with_capacity(df.height());
+    let mut commission_cents: Vec<Option<i64>> = Vec::with_capacity(df.height());
+    let mut errors: Vec<Option<String>> = Vec::with_capacity(df.height());
+    for row in 0..df.height() {
+        // Earnings that did not parse have no currency to convert from
+        let Some(currency) = currencies.get(row) else {
+            rates.push(None);
+            amount_cents.push(None);
+            commission_cents.push(None);
+            errors.push(None);
+            continue;
+        };
+        let converted = start_dates[row]
+            .or(booked_dates[row])
+            .ok_or_else(|| Error::parse("exchange rate", "the reservation has no dates"))
+            .and_then(|date| provider.rate(currency, reporting_currency, date))
+            .and_then(|rate| {
+                let convert = |cents: Option<i64>| {
+                    cents
+                        .map(|cents| apply_rate(cents, rate, rounding))
+                        .transpose()
+                };
+                let commission = match &commissions {
+                    Some(commissions) => convert(commissions.get(row))?,
+                    None => None,
+                };
+                Ok((rate, convert(amounts.get(row))?, commission))
+            });
+        match converted {
+            Ok((rate, amount, commission)) => {
+                rates.push(rate.to_f64());
+                amount_cents.push(amount);
+                commission_cents.push(commission);
+                errors.push(None);
+            }
+            Err(e) => {
+                rates.push(None);
+                amount_cents.push(None);
+                commission_cents.push(None);
+                errors.push(Some(e.to_string()));
+            }
+        }
+    }
+
+    let mut columns = vec![
+        Series::new(
+            "reporting_currency",
+            vec![reporting_currency.to_string(); df.height()],
+        ),
+        Series::new("fx_rate", rates),
+        Series::new("converted_amount", cents_to_amounts(&amount_cents)),
+        Series::new("converted_amount_cents", amount_cents),
+    ];
+    if commissions.is_some() {
+        columns.push(Series::new(
+            "converted_commission_earnings",
+            cents_to_amounts(&commission_cents),
+        ));
+        columns.push(Series::new("converted_commission_cents", commission_cents));
+    }
+    if errors.iter().any(Option::is_some) {
+        columns.push(Series::new("fx_error", errors));
+    }
+    Ok(df.hstack(&columns)?)
+}
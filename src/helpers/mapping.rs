@@ -0,0 +1,221 @@
+//! Integrity of the listing and owner sheets of the base workbook against
+//! the reservations they are joined to.
+use super::columns::text_column;
+use super::registry::{ListingRegistry, MatchKind};
+use crate::error::Result;
+use polars::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// What is wrong with a key of the base workbook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IssueKind {
+    /// A listing of the reservations missing from the listings sheet.
+    UnmappedListing,
+    /// A listing of the reservations matched only by a similar name.
+    ApproximateMatch,
+    /// A listing name or ID the listings sheet has more than once.
+    DuplicateListing,
+    /// An owner of the listings sheet missing from the owners sheet.
+    UnknownOwner,
+    /// A listing of the listings sheet no reservation has.
+    UnusedListing,
+    /// A reservation whose earnings do not parse.
+    InvalidEarnings,
+    /// A reservation no commission rule applies to, or whose fees do not
+    /// parse.
+    NoCommission,
+    /// A reservation with no exchange rate into the reporting currency.
+    NoExchangeRate,
+}
+
+impl IssueKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            IssueKind::UnmappedListing => "Unmapped listing",
+            IssueKind::ApproximateMatch => "Approximate match",
+            IssueKind::DuplicateListing => "Duplicate listing",
+            IssueKind::UnknownOwner => "Unknown owner",
+            IssueKind::UnusedListing => "Unused listing",
+            IssueKind::InvalidEarnings => "Invalid earnings",
+            IssueKind::NoCommission => "No commission",
+            IssueKind::NoExchangeRate => "No exchange rate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub kind: IssueKind,
+    /// Listing name, listing ID or owner.
+    pub key: String,
+    pub detail: String,
+}
+
+/// Distinct non-empty values of a column, with the rows they are on.
+fn rows_by_value(df: &DataFrame, name: &str) -> Result<BTreeMap<String, Vec<usize>>> {
+    let mut values: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let column = df.column(name)?.cast(&DataType::String)?;
+    for (row, value) in column.str()?.into_iter().enumerate() {
+        if let Some(value) = value.filter(|value| !value.trim().is_empty()) {
+            values.entry(value.to_string()).or_default().push(row);
+        }
+    }
+    Ok(values)
+}
+
+/// Checks the matches of `reservations` with the `registry` read from the
+/// `listings` sheet (with the `PROPIETARIO` of each listing) and the join of
+/// the listings with the `owners` sheet (by `PROPIETARIO`).
+///
+/// Reports the listings of the reservations the registry does not match,
+/// which get no owner and the default commission; the ones it matches only
+/// by a similar name; the names and listing IDs the sheet repeats, of which
+/// only the first row is ever matched; the owners missing from the owners
+/// sheet; and the listings no reservation matches. Issues are sorted by kind
+/// and key.
+pub fn validate(
+    reservations: &DataFrame,
+    registry: &ListingRegistry,
+    listings: &DataFrame,
+    owners: &DataFrame,
+) -> Result<Vec<Issue>> {
+    let matches = registry.resolve(reservations)?;
+    let known_owners = rows_by_value(owners, "PROPIETARIO")?;
+    let codes = reservations.column("confirmation_code")?.str()?;
+    let names = reservations.column("listing_name")?.str()?;
+    let listing_names = listings.column("ANUNCIO")?.cast(&DataType::String)?;
+    let listing_names = listing_names.str()?;
+
+    let mut unmapped: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    let mut approximate: BTreeMap<&str, (usize, f64)> = BTreeMap::new();
+    let mut matched: BTreeSet<usize> = BTreeSet::new();
+    for (row, found) in matches.iter().enumerate() {
+        let name = names.get(row).unwrap_or_default();
+        match found {
+            None => unmapped.entry(name).or_default().push(row),
+            Some(found) => {
+                matched.insert(found.row);
+                if found.kind == MatchKind::Fuzzy {
+                    approximate.insert(name, (found.row, found.score));
+                }
+            }
+        }
+    }
+
+    let mut issues: Vec<Issue> = Vec::new();
+    for (listing, rows) in unmapped {
+        let codes: Vec<&str> = rows.iter().filter_map(|row| codes.get(*row)).collect();
+        issues.push(Issue {
+            kind: IssueKind::UnmappedListing,
+            key: listing.to_string(),
+            detail: format!(
+                "{} reservations without owner, on the default commission: {}",
+                rows.len(),
+                codes.join(", ")
+            ),
+        });
+    }
+    for (listing, (row, score)) in approximate {
+        issues.push(Issue {
+            kind: IssueKind::ApproximateMatch,
+            key: listing.to_string(),
+            detail: format!(
+                "matched to {} (line {} of the listings sheet) with a similarity of {:.0}%, add it as an alias or set the listing ID",
+                listing_names.get(row).unwrap_or_default(),
+                row + 2,
+                score * 100.0
+            ),
+        });
+    }
+    let mut keys = vec![("name", rows_by_value(listings, "ANUNCIO")?)];
+    if listings.column("ID_ANUNCIO").is_ok() {
+        keys.push(("listing ID", rows_by_value(listings, "ID_ANUNCIO")?));
+    }
+    for (what, values) in keys {
+        for (key, rows) in values.into_iter().filter(|(_, rows)| rows.len() > 1) {
+            issues.push(Issue {
+                kind: IssueKind::DuplicateListing,
+                key,
+                detail: format!(
+                    "{} rows with this {} in the listings sheet (lines {}), only the first is matched",
+                    rows.len(),
+                    what,
+                    lines(&rows)
+                ),
+            });
+        }
+    }
+    // Rows repeating a matched name are reported as duplicates only
+    let mut used: BTreeSet<&str> = matched
+        .iter()
+        .filter_map(|row| listing_names.get(*row))
+        .collect();
+    for row in registry.rows() {
+        let listing = listing_names.get(row).unwrap_or_default();
+        if used.insert(listing) {
+            issues.push(Issue {
+                kind: IssueKind::UnusedListing,
+                key: listing.to_string(),
+                detail: format!(
+                    "no reservation matches this listing (line {} of the listings sheet)",
+                    row + 2
+                ),
+            });
+        }
+    }
+
+    for (owner, rows) in rows_by_value(listings, "PROPIETARIO")? {
+        if !known_owners.contains_key(&owner) {
+            let names: BTreeSet<&str> = rows
+                .iter()
+                .filter_map(|row| listing_names.get(*row))
+                .collect();
+            issues.push(Issue {
+                kind: IssueKind::UnknownOwner,
+                key: owner,
+                detail: format!(
+                    "missing from the owners sheet, owner of {}",
+                    names.into_iter().collect::<Vec<_>>().join(", ")
+                ),
+            });
+        }
+    }
+
+    issues.sort_by(|a, b| (a.kind, &a.key).cmp(&(b.kind, &b.key)));
+    Ok(issues)
+}
+
+/// Columns the processing stages mark the reservations they could not price
+/// with, and the issue each one is reported as.
+const ROW_ERRORS: [(&str, IssueKind); 3] = [
+    ("earnings_error", IssueKind::InvalidEarnings),
+    ("commission_error", IssueKind::NoCommission),
+    ("fx_error", IssueKind::NoExchangeRate),
+];
+
+/// One issue per reservation of `df` marked by a processing stage, keyed by
+/// its confirmation code, in the order of the stages and the rows.
+pub fn row_issues(df: &DataFrame) -> Result<Vec<Issue>> {
+    let codes = text_column(df, "confirmation_code")?;
+    let mut issues: Vec<Issue> = Vec::new();
+    for (column, kind) in ROW_ERRORS {
+        for (code, error) in codes.iter().zip(text_column(df, column)?) {
+            if let Some(detail) = error {
+                issues.push(Issue {
+                    kind,
+                    key: code.clone().unwrap_or_default(),
+                    detail,
+                });
+            }
+        }
+    }
+    Ok(issues)
+}
+
+/// Sheet lines of frame rows, the header being line 1.
+fn lines(rows: &[usize]) -> String {
+    rows.iter()
+        .map(|row| (row + 2).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -1,2 +1,12 @@
+pub mod columns;
+pub mod commission;
+pub mod dates;
+pub mod diff;
 pub mod engine;
 pub mod excel;
+pub mod fx;
+pub mod mapping;
+pub mod money;
+pub mod registry;
+pub mod statement;
+pub mod summary;
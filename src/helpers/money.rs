@@ -0,0 +1,261 @@
+//! Amounts as Airbnb formats them in each locale, e.g. `S/ 1,250.50`,
+//! `1.250,50 €`, `-US$120`, `R$ 1.250,50` or `CHF 1'250.50`.
+use crate::error::{Error, Result};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::fmt;
+use std::str::FromStr;
+
+/// An amount with the ISO 4217 code of its currency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Money {
+    pub currency: String,
+    pub amount: Decimal,
+}
+
+/// How commissions and converted amounts are rounded to cents, set by
+/// `COMMISSION_ROUNDING`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rounding {
+    /// 0.5 cents away from zero.
+    #[default]
+    HalfUp,
+    /// 0.5 cents towards zero.
+    HalfDown,
+    /// 0.5 cents to the even cent (banker's rounding).
+    HalfEven,
+    /// Any fraction away from zero.
+    Up,
+    /// Any fraction towards zero (truncation).
+    Down,
+}
+
+impl Rounding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rounding::HalfUp => "half-up",
+            Rounding::HalfDown => "half-down",
+            Rounding::HalfEven => "half-even",
+            Rounding::Up => "up",
+            Rounding::Down => "down",
+        }
+    }
+
+    fn strategy(&self) -> RoundingStrategy {
+        match self {
+            Rounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Rounding::HalfDown => RoundingStrategy::MidpointTowardZero,
+            Rounding::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Rounding::Up => RoundingStrategy::AwayFromZero,
+            Rounding::Down => RoundingStrategy::ToZero,
+        }
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "half-up" => Ok(Rounding::HalfUp),
+            "half-down" => Ok(Rounding::HalfDown),
+            "half-even" => Ok(Rounding::HalfEven),
+            "up" => Ok(Rounding::Up),
+            "down" => Ok(Rounding::Down),
+            other => Err(Error::Config(format!(
+                "Unknown rounding mode: {} (expected half-up, half-down, half-even, up or down)",
+                other
+            ))),
+        }
+    }
+}
+
+/// `amount` in whole cents.
+pub fn to_cents(amount: Decimal, rounding: Rounding) -> Result<i64> {
+    (amount * Decimal::ONE_HUNDRED)
+        .round_dp_with_strategy(0, rounding.strategy())
+        .to_i64()
+        .ok_or_else(|| Error::parse("amount", format!("{} is out of range", amount)))
+}
+
+/// Cents as a decimal amount, e.g. `125050` is `1250.50`.
+pub fn from_cents(cents: i64) -> Decimal {
+    Decimal::new(cents, 2)
+}
+
+/// Cents as the amounts written to the workbooks, exact to the cent; missing
+/// cents stay missing.
+pub fn cents_to_amounts<T: Copy + Into<Option<i64>>>(cents: &[T]) -> Vec<Option<f64>> {
+    cents
+        .iter()
+        .map(|cents| {
+            (*cents)
+                .into()
+                .map(|cents| from_cents(cents).to_f64().unwrap_or_default())
+        })
+        .collect()
+}
+
+/// `cents` times `rate`, rounded to whole cents: a commission with a rate
+/// such as `0.15` for 15%, or a conversion with an exchange rate.
+pub fn apply_rate(cents: i64, rate: Decimal, rounding: Rounding) -> Result<i64> {
+    (Decimal::from(cents) * rate)
+        .round_dp_with_strategy(0, rounding.strategy())
+        .to_i64()
+        .ok_or_else(|| {
+            Error::parse(
+                "amount",
+                format!("{} times {} cents is out of range", rate, cents),
+            )
+        })
+}
+
+/// Currency symbols and their ISO codes.
+const SYMBOLS: [(&str, &str); 26] = [
+    ("S/.", "PEN"),
+    ("S/", "PEN"),
+    ("US$", "USD"),
+    ("CA$", "CAD"),
+    ("AU$", "AUD"),
+    ("A$", "AUD"),
+    ("MX$", "MXN"),
+    ("NZ$", "NZD"),
+    ("HK$", "HKD"),
+    ("COL$", "COP"),
+    ("CLP$", "CLP"),
+    ("R$", "BRL"),
+    ("€", "EUR"),
+    ("£", "GBP"),
+    ("¥", "JPY"),
+    ("₩", "KRW"),
+    ("₹", "INR"),
+    ("₱", "PHP"),
+    ("₺", "TRY"),
+    ("₪", "ILS"),
+    ("zł", "PLN"),
+    ("Kč", "CZK"),
+    ("Ft", "HUF"),
+    ("Fr.", "CHF"),
+    ("R", "ZAR"),
+    ("Rp", "IDR"),
+];
+
+/// Currencies Airbnb writes with a bare `$`.
+const DOLLAR_CURRENCIES: [&str; 11] = [
+    "USD", "CAD", "AUD", "MXN", "ARS", "CLP", "COP", "NZD", "HKD", "SGD", "UYU",
+];
+
+/// Grouping separators besides `.` and `,`: spaces, no-break spaces and the
+/// Swiss apostrophe.
+const GROUPING: [char; 4] = [' ', '\u{a0}', '\u{202f}', '\''];
+
+fn currency_code(symbol: &str, default_currency: &str) -> Option<String> {
+    if symbol.is_empty() {
+        return Some(default_currency.to_string());
+    }
+    if symbol == "$" {
+        let dollar = DOLLAR_CURRENCIES.contains(&default_currency);
+        return Some(if dollar { default_currency } else { "USD" }.to_string());
+    }
+    if symbol.len() == 3 && symbol.chars().all(|c| c.is_ascii_uppercase()) {
+        return Some(symbol.to_string());
+    }
+    SYMBOLS
+        .iter()
+        .find(|(known, _)| *known == symbol)
+        .map(|(_, code)| code.to_string())
+}
+
+/// Which of `.` and `,` separates the decimals, if any. With both, the last
+/// one does; a lone separator followed by exactly three digits groups
+/// thousands (`1,250` is 1250, `1,25` is 1.25).
+fn decimal_separator(number: &str) -> Option<char> {
+    match (number.rfind('.'), number.rfind(',')) {
+        (Some(dot), Some(comma)) => Some(if dot > comma { '.' } else { ',' }),
+        (Some(index), None) | (None, Some(index)) => {
+            let separator = number[index..].chars().next()?;
+            let groups = number.matches(separator).count();
+            let decimals = number.len() - index - 1;
+            (groups == 1 && decimals != 3).then_some(separator)
+        }
+        (None, None) => None,
+    }
+}
+
+fn parse_amount(number: &str) -> Option<Decimal> {
+    let number: String = number.chars().filter(|c| !GROUPING.contains(c)).collect();
+    let decimal = decimal_separator(&number);
+    let mut normalized = String::with_capacity(number.len());
+    let mut seen_decimal = false;
+    for c in number.chars() {
+        match c {
+            '0'..='9' => normalized.push(c),
+            '.' | ',' if Some(c) == decimal && !seen_decimal => {
+                seen_decimal = true;
+                normalized.push('.');
+            }
+            // A grouping separator after the decimals is malformed
+            '.' | ',' if Some(c) != decimal && !seen_decimal => {}
+            _ => return None,
+        }
+    }
+    Decimal::from_str(&normalized).ok()
+}
+
+/// Parses an amount written by Airbnb.
+///
+/// The currency symbol may come before or after the number, with or without
+/// a space; a bare number or an ambiguous `$` is taken to be in
+/// `default_currency` (the currency the amounts were requested in). A
+/// leading `-`/`−` on either side of the symbol or accounting parentheses
+/// make the amount negative.
+pub fn parse_money(input: &str, default_currency: &str) -> Result<Money> {
+    let error = |message: &str| Error::parse("amount", format!("{:?} {}", input, message));
+    let trimmed = input.trim();
+    let first = trimmed
+        .find(|c: char| c.is_ascii_digit())
+        .ok_or_else(|| error("has no digits"))?;
+    let last = trimmed
+        .rfind(|c: char| c.is_ascii_digit())
+        .ok_or_else(|| error("has no digits"))?;
+    let (prefix, rest) = trimmed.split_at(first);
+    let (number, suffix) = rest.split_at(last - first + 1);
+
+    let mut negative = false;
+    let mut parentheses = 0;
+    let symbol: String = prefix
+        .chars()
+        .chain(suffix.chars())
+        .filter(|c| match c {
+            '-' | '−' => {
+                negative = true;
+                false
+            }
+            '(' | ')' => {
+                parentheses += 1;
+                false
+            }
+            '+' => false,
+            c => !c.is_whitespace(),
+        })
+        .collect();
+    if parentheses == 2 {
+        negative = true;
+    } else if parentheses != 0 {
+        return Err(error("has unbalanced parentheses"));
+    }
+
+    let currency = currency_code(&symbol, default_currency)
+        .ok_or_else(|| error(&format!("has an unknown currency {:?}", symbol)))?;
+    let amount = parse_amount(number).ok_or_else(|| error("is not a number"))?;
+    Ok(Money {
+        currency,
+        amount: if negative { -amount } else { amount },
+    })
+}
@@ -0,0 +1,234 @@
+//! Registry of the listings in the first sheet of the base workbook, matched
+//! to the reservations by Airbnb listing ID or by name.
+use super::columns::text_column;
+use crate::error::Result;
+use polars::prelude::*;
+use std::fmt;
+
+/// How a reservation was matched to its registry entry, from the most to
+/// the least reliable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// Same Airbnb listing ID.
+    Id,
+    /// Same name as the canonical one.
+    Name,
+    /// Same name as one of the aliases.
+    Alias,
+    /// Same name or alias once normalized.
+    Normalized,
+    /// Closest name or alias, above the threshold.
+    Fuzzy,
+}
+
+impl MatchKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchKind::Id => "id",
+            MatchKind::Name => "name",
+            MatchKind::Alias => "alias",
+            MatchKind::Normalized => "normalized",
+            MatchKind::Fuzzy => "fuzzy",
+        }
+    }
+}
+
+impl fmt::Display for MatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The registry entry of a reservation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ListingMatch {
+    /// Row of the entry in the sheet.
+    pub row: usize,
+    pub kind: MatchKind,
+    /// Similarity of the names, 1 for exact matches.
+    pub score: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    row: usize,
+    listing_id: Option<String>,
+    name: String,
+    aliases: Vec<String>,
+    /// Normalized name and aliases.
+    normalized: Vec<String>,
+}
+
+/// Lowercase letters and digits without accents, one space between words.
+pub fn normalize(name: &str) -> String {
+    let unaccented: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ä' | 'ã' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'ö' | 'õ' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ñ' => 'n',
+            'ç' => 'c',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect();
+    unaccented.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 1 minus the edit distance over the length of the longer name.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+/// Listings of the first sheet: `ANUNCIO` is the canonical name, and the
+/// optional `ID_ANUNCIO` and `ALIAS` columns hold the Airbnb listing ID and
+/// the former names separated by `|`. Every other column (`PROPIETARIO`,
+/// the zone, `CAPACIDAD`, `DIRECCION`...) is joined to the matched
+/// reservations as is.
+#[derive(Debug, Clone)]
+pub struct ListingRegistry {
+    entries: Vec<Entry>,
+    threshold: f64,
+}
+
+impl ListingRegistry {
+    /// Reads the registry sheet; fuzzy matches need a similarity of at least
+    /// `threshold`.
+    pub fn from_dataframe(df: &DataFrame, threshold: f64) -> std::result::Result<Self, String> {
+        if df.column("ANUNCIO").is_err() {
+            return Err("missing column ANUNCIO".to_string());
+        }
+        let read = |name: &str| text_column(df, name).map_err(|e| e.to_string());
+        let (names, ids, aliases) = (read("ANUNCIO")?, read("ID_ANUNCIO")?, read("ALIAS")?);
+
+        let mut entries = Vec::new();
+        for (row, ((name, listing_id), aliases)) in
+            names.into_iter().zip(ids).zip(aliases).enumerate()
+        {
+            let Some(name) = name else {
+                continue;
+            };
+            let aliases: Vec<String> = aliases
+                .unwrap_or_default()
+                .split('|')
+                .map(str::trim)
+                .filter(|alias| !alias.is_empty())
+                .map(str::to_string)
+                .collect();
+            let normalized = std::iter::once(&name)
+                .chain(&aliases)
+                .map(|name| normalize(name))
+                .collect();
+            entries.push(Entry {
+                row,
+                listing_id,
+                name,
+                aliases,
+                normalized,
+            });
+        }
+        Ok(ListingRegistry { entries, threshold })
+    }
+
+    /// Rows of the entries, in sheet order.
+    pub fn rows(&self) -> impl Iterator<Item = usize> + '_ {
+        self.entries.iter().map(|entry| entry.row)
+    }
+
+    /// The entry of a reservation: the one with its listing ID, or else the
+    /// one with its name, alias, normalized name or closest name. Entries
+    /// with another listing ID are never matched by name, and a fuzzy match
+    /// must be the only closest entry.
+    pub fn find(&self, listing_id: &str, name: &str) -> Option<ListingMatch> {
+        let exact = |entry: &Entry, kind: MatchKind| ListingMatch {
+            row: entry.row,
+            kind,
+            score: 1.0,
+        };
+        let listing_id = listing_id.trim();
+        if !listing_id.is_empty() {
+            if let Some(entry) = self
+                .entries
+                .iter()
+                .find(|entry| entry.listing_id.as_deref() == Some(listing_id))
+            {
+                return Some(exact(entry, MatchKind::Id));
+            }
+        }
+
+        let candidates: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(|entry| listing_id.is_empty() || entry.listing_id.is_none())
+            .collect();
+        let name = name.trim();
+        if let Some(entry) = candidates.iter().find(|entry| entry.name == name) {
+            return Some(exact(entry, MatchKind::Name));
+        }
+        if let Some(entry) = candidates
+            .iter()
+            .find(|entry| entry.aliases.iter().any(|alias| alias == name))
+        {
+            return Some(exact(entry, MatchKind::Alias));
+        }
+        let normalized = normalize(name);
+        if let Some(entry) = candidates
+            .iter()
+            .find(|entry| entry.normalized.contains(&normalized))
+        {
+            return Some(exact(entry, MatchKind::Normalized));
+        }
+
+        let scores: Vec<(&Entry, f64)> = candidates
+            .iter()
+            .map(|entry| {
+                let score = entry
+                    .normalized
+                    .iter()
+                    .map(|known| similarity(known, &normalized))
+                    .fold(0.0, f64::max);
+                (*entry, score)
+            })
+            .collect();
+        let best = scores.iter().map(|(_, score)| *score).fold(0.0, f64::max);
+        let mut closest = scores.iter().filter(|(_, score)| *score == best);
+        match (closest.next(), closest.next()) {
+            (Some((entry, score)), None) if *score >= self.threshold => Some(ListingMatch {
+                row: entry.row,
+                kind: MatchKind::Fuzzy,
+                score: *score,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The entry of every reservation of `df`, by its `listing_id` (when the
+    /// frame has it) and `listing_name`.
+    pub fn resolve(&self, df: &DataFrame) -> Result<Vec<Option<ListingMatch>>> {
+        let ids = text_column(df, "listing_id")?;
+        let names = df.column("listing_name")?.str()?;
+        Ok(ids
+            .into_iter()
+            .zip(names)
+            .map(|(id, name)| self.find(&id.unwrap_or_default(), name.unwrap_or_default()))
+            .collect())
+    }
+}
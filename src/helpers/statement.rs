@@ -0,0 +1,121 @@
+//! Statements of the consolidated reservations, one per owner.
+use super::columns::date_column;
+use super::commission::CommissionDate;
+use super::summary::{summarize, ListingCounts, Summary};
+use crate::error::{Error, Result};
+use chrono::NaiveDate;
+use polars::prelude::*;
+use std::collections::BTreeSet;
+
+/// The reservations of one owner with their totals.
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub owner: String,
+    /// Zones of the owner's listings, sorted.
+    pub zones: Vec<String>,
+    /// Period given, or else from the first check-in to the last checkout.
+    pub period: Option<(NaiveDate, NaiveDate)>,
+    pub reservations: DataFrame,
+    /// Reservations, nights, gross earnings, commission and net payout per
+    /// currency.
+    pub totals: DataFrame,
+    /// The same totals per listing.
+    pub listings: DataFrame,
+}
+
+fn sheet(summaries: &mut Vec<Summary>, name: &str) -> Result<DataFrame> {
+    let index = summaries
+        .iter()
+        .position(|summary| summary.sheet == name)
+        .ok_or_else(|| Error::parse("statement", format!("no {} totals", name)))?;
+    Ok(summaries.swap_remove(index).df)
+}
+
+/// First check-in and last checkout of `df`.
+fn span(df: &DataFrame) -> Result<Option<(NaiveDate, NaiveDate)>> {
+    let start = date_column(df, "start_date")?.into_iter().flatten().min();
+    let end = date_column(df, "end_date")?.into_iter().flatten().max();
+    Ok(start.zip(end))
+}
+
+/// The reservations of `df` whose `reference` date, or check-in when it is
+/// missing, falls in `period`.
+fn in_period(
+    df: &DataFrame,
+    reference: CommissionDate,
+    (first, last): (NaiveDate, NaiveDate),
+) -> Result<DataFrame> {
+    let check_ins = date_column(df, "start_date")?;
+    let mask: Vec<bool> = date_column(df, reference.column())?
+        .into_iter()
+        .zip(check_ins)
+        .map(|(date, check_in)| {
+            date.or(check_in)
+                .is_some_and(|date| first <= date && date <= last)
+        })
+        .collect();
+    Ok(df.filter(&BooleanChunked::new("in_period", mask))?)
+}
+
+/// One statement per `PROPIETARIO` of the consolidated `df`, sorted by owner.
+/// `zone_column` holds the zone of each listing, and `period`, when given,
+/// is the one of every statement: only the reservations whose `reference`
+/// date (or check-in) falls in it are included, and occupancy is measured
+/// against it, over all the owner's listings in `registry` when given.
+/// Reservations without an owner are left out; they are reported as
+/// unmapped listings.
+pub fn statements(
+    df: &DataFrame,
+    zone_column: &str,
+    reference: CommissionDate,
+    period: Option<(NaiveDate, NaiveDate)>,
+    registry: Option<&ListingCounts>,
+) -> Result<Vec<Statement>> {
+    let df = &match period {
+        Some(period) => in_period(df, reference, period)?,
+        None => df.clone(),
+    };
+    let owners: BTreeSet<String> = df
+        .column("PROPIETARIO")
+        .map_err(|_| Error::parse("consolidated data", "missing column PROPIETARIO"))?
+        .cast(&DataType::String)?
+        .str()?
+        .into_iter()
+        .flatten()
+        .filter(|owner| !owner.trim().is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let mut statements = Vec::new();
+    for owner in owners {
+        let reservations = df
+            .clone()
+            .lazy()
+            .filter(col("PROPIETARIO").eq(lit(owner.as_str())))
+            .collect()?;
+        let zones: BTreeSet<String> = match reservations.column(zone_column) {
+            Ok(zones) => zones
+                .cast(&DataType::String)?
+                .str()?
+                .into_iter()
+                .flatten()
+                .filter(|zone| !zone.trim().is_empty())
+                .map(str::to_string)
+                .collect(),
+            Err(_) => BTreeSet::new(),
+        };
+
+        let mut summaries = summarize(&reservations, zone_column, period, registry)?;
+        let totals = sheet(&mut summaries, "By owner")?.drop("PROPIETARIO")?;
+        let listings = sheet(&mut summaries, "By listing")?;
+        statements.push(Statement {
+            period: period.or(span(&reservations)?),
+            owner,
+            zones: zones.into_iter().collect(),
+            reservations,
+            totals,
+            listings,
+        });
+    }
+    Ok(statements)
+}
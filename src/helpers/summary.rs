@@ -0,0 +1,310 @@
+//! Totals of the consolidated reservations by listing, owner, zone and month.
+use super::columns::{date_column, text_column};
+use crate::error::Result;
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use polars::prelude::*;
+use std::collections::HashMap;
+
+/// Nights of each reservation inside the occupancy period, summed with the
+/// totals and dropped once the occupancy is computed.
+const OCCUPIED_NIGHTS: &str = "occupied_nights";
+
+/// One sheet of the summary report.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub sheet: String,
+    pub df: DataFrame,
+}
+
+/// Listings of the registry (the first sheet of the base workbook) per
+/// owner and zone, what their occupancy is measured against.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListingCounts {
+    pub total: usize,
+    pub by_owner: HashMap<String, usize>,
+    pub by_zone: HashMap<String, usize>,
+}
+
+impl ListingCounts {
+    /// Counts the listings of the registry sheet by `PROPIETARIO` and by
+    /// `zone_column`; rows without an `ANUNCIO` are left out.
+    pub fn from_registry(df: &DataFrame, zone_column: &str) -> Result<Self> {
+        let names = text_column(df, "ANUNCIO")?;
+        let owners = text_column(df, "PROPIETARIO")?;
+        let zones = text_column(df, zone_column)?;
+
+        let mut counts = ListingCounts::default();
+        for ((name, owner), zone) in names.iter().zip(owners).zip(zones) {
+            if name.is_none() {
+                continue;
+            }
+            counts.total += 1;
+            if let Some(owner) = owner {
+                *counts.by_owner.entry(owner).or_default() += 1;
+            }
+            if let Some(zone) = zone {
+                *counts.by_zone.entry(zone).or_default() += 1;
+            }
+        }
+        Ok(counts)
+    }
+}
+
+/// Amount columns to add up: the converted ones when the reservations were
+/// converted into a reporting currency.
+fn amount_columns(df: &DataFrame) -> (&'static str, &'static str, &'static str) {
+    if df.column("converted_amount_cents").is_ok() {
+        (
+            "reporting_currency",
+            "converted_amount_cents",
+            "converted_commission_cents",
+        )
+    } else {
+        ("currency", "amount_cents", "commission_cents")
+    }
+}
+
+/// The canonical name of the listing of each reservation when the registry
+/// matched it, its Airbnb name otherwise.
+fn listing(df: &DataFrame) -> Expr {
+    if df.column("ANUNCIO").is_ok() {
+        coalesce(&[col("ANUNCIO"), col("listing_name")])
+    } else {
+        col("listing_name")
+    }
+}
+
+fn cents_to_units(name: &str) -> Expr {
+    (col(name).cast(DataType::Float64) / lit(100.0)).alias(name.trim_end_matches("_cents"))
+}
+
+/// Reservations, nights, gross earnings, commission and net to owner per
+/// `key` and currency, sorted by `key`, with the `OCCUPIED_NIGHTS` of `df`.
+fn totals(df: &DataFrame, key: Expr, key_name: &str) -> Result<DataFrame> {
+    let (currency, amount, commission) = amount_columns(df);
+    Ok(df
+        .clone()
+        .lazy()
+        .group_by([key.alias(key_name), col(currency).alias("currency")])
+        .agg([
+            col("confirmation_code").count().alias("reservations"),
+            listing(df).n_unique().alias("listings"),
+            col("nights").sum().alias("nights"),
+            col(OCCUPIED_NIGHTS).sum().alias(OCCUPIED_NIGHTS),
+            col(amount).sum().alias("gross_cents"),
+            col(commission).sum().alias("commission_cents"),
+        ])
+        .with_column((col("gross_cents") - col("commission_cents")).alias("net_cents"))
+        .with_columns([
+            cents_to_units("gross_cents"),
+            cents_to_units("commission_cents"),
+            cents_to_units("net_cents"),
+        ])
+        .select([
+            col(key_name),
+            col("currency"),
+            col("reservations"),
+            col("listings"),
+            col("nights"),
+            col("gross"),
+            col("commission"),
+            col("net"),
+            col(OCCUPIED_NIGHTS),
+        ])
+        .sort(
+            [key_name, "currency"],
+            SortMultipleOptions::new().with_nulls_last(true),
+        )
+        .collect()?)
+}
+
+/// Replaces the `listings` of each row by the registry count of its key,
+/// where `count` has one.
+fn registry_listings(
+    mut df: DataFrame,
+    key_name: &str,
+    count: impl Fn(&str) -> Option<usize>,
+) -> Result<DataFrame> {
+    let keys = text_column(&df, key_name)?;
+    let listings = df.column("listings")?.cast(&DataType::UInt32)?;
+    let listings: Vec<Option<u32>> = keys
+        .iter()
+        .zip(listings.u32()?)
+        .map(|(key, listings)| {
+            key.as_deref()
+                .and_then(&count)
+                .map(|count| count as u32)
+                .or(listings)
+        })
+        .collect();
+    df.with_column(Series::new("listings", listings))?;
+    Ok(df)
+}
+
+/// `occupied` nights of each row over the nights its listings could be
+/// booked in `days`; `None` where the number of days is unknown.
+fn with_occupancy(
+    mut df: DataFrame,
+    occupied: impl Fn(usize) -> f64,
+    days: impl Fn(usize) -> Option<i64>,
+) -> Result<DataFrame> {
+    let listings = df.column("listings")?.cast(&DataType::Float64)?;
+    let listings = listings.f64()?;
+    let occupancy: Vec<Option<f64>> = (0..df.height())
+        .map(|row| {
+            let available = listings.get(row)? * days(row)? as f64;
+            (available > 0.0).then(|| occupied(row) / available)
+        })
+        .collect();
+    df.with_column(Series::new("occupancy", occupancy))?;
+    Ok(df)
+}
+
+/// First and last day of a `YYYY-MM` month.
+fn month_bounds(month: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let first = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").ok()?;
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+    Some((first, last))
+}
+
+/// Check-in and checkout of every reservation.
+fn stays(df: &DataFrame) -> Result<Vec<Option<(NaiveDate, NaiveDate)>>> {
+    let ends = date_column(df, "end_date")?;
+    Ok(date_column(df, "start_date")?
+        .into_iter()
+        .zip(ends)
+        .map(|(start, end)| start.zip(end))
+        .collect())
+}
+
+/// Nights of a stay between `first` and `last`, both included.
+fn nights_within(stay: (NaiveDate, NaiveDate), first: NaiveDate, last: NaiveDate) -> f64 {
+    let (check_in, checkout) = stay;
+    let nights = (checkout.min(last + Duration::days(1)) - check_in.max(first)).num_days();
+    nights.max(0) as f64
+}
+
+/// Nights booked in each month and currency, a stay counting in every month
+/// it runs through.
+fn nights_by_month(
+    stays: &[Option<(NaiveDate, NaiveDate)>],
+    currencies: &[Option<String>],
+) -> HashMap<(String, String), f64> {
+    let mut nights: HashMap<(String, String), f64> = HashMap::new();
+    for (stay, currency) in stays.iter().zip(currencies) {
+        let Some((check_in, checkout)) = *stay else {
+            continue;
+        };
+        let mut month = check_in.with_day(1);
+        while let Some(first) = month.filter(|first| *first < checkout) {
+            let month_name = first.format("%Y-%m").to_string();
+            if let Some((first, last)) = month_bounds(&month_name) {
+                *nights
+                    .entry((month_name, currency.clone().unwrap_or_default()))
+                    .or_default() += nights_within((check_in, checkout), first, last);
+            }
+            month = first.checked_add_months(Months::new(1));
+        }
+    }
+    nights
+}
+
+/// Summaries of the consolidated reservations, one per sheet.
+///
+/// `period` is the date window of the fetch, when it has both bounds; it is
+/// what the occupancy of the listing, owner and zone summaries is measured
+/// against. Months are those of the start dates, and their occupancy is
+/// always known. Occupancy only counts the nights inside the period or the
+/// month, and measures owners, zones and months against all their listings
+/// in `registry`, when given, not only the booked ones. The owner and zone
+/// summaries are only built when the base workbook has those columns.
+pub fn summarize(
+    df: &DataFrame,
+    zone_column: &str,
+    period: Option<(NaiveDate, NaiveDate)>,
+    registry: Option<&ListingCounts>,
+) -> Result<Vec<Summary>> {
+    let period_days = period.map(|(start, end)| (end - start).num_days() + 1);
+    let stays = stays(df)?;
+    let occupied: Vec<f64> = stays
+        .iter()
+        .map(|stay| match (stay, period) {
+            (Some(stay), Some((first, last))) => nights_within(*stay, first, last),
+            _ => 0.0,
+        })
+        .collect();
+    let mut df = df.clone();
+    df.with_column(Series::new(OCCUPIED_NIGHTS, occupied))?;
+    let df = &df;
+
+    let keys = [
+        ("By listing", "listing_name", None),
+        (
+            "By owner",
+            "PROPIETARIO",
+            registry.map(|counts| &counts.by_owner),
+        ),
+        (
+            "By zone",
+            zone_column,
+            registry.map(|counts| &counts.by_zone),
+        ),
+    ];
+
+    let mut summaries = Vec::new();
+    for (sheet, name, counts) in keys {
+        let key = match name {
+            "listing_name" => listing(df),
+            _ if df.column(name).is_ok() => col(name),
+            _ => continue,
+        };
+        let by_key = registry_listings(totals(df, key, name)?, name, |key| {
+            counts.and_then(|counts| counts.get(key).copied())
+        })?;
+        let occupied = by_key.column(OCCUPIED_NIGHTS)?.cast(&DataType::Float64)?;
+        let occupied: Vec<f64> = occupied
+            .f64()?
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+        let df = with_occupancy(
+            by_key.drop(OCCUPIED_NIGHTS)?,
+            |row| occupied[row],
+            |_| period_days,
+        )?;
+        summaries.push(Summary {
+            sheet: sheet.to_string(),
+            df,
+        });
+    }
+
+    let month = col("start_date").dt().strftime("%Y-%m");
+    let by_month = totals(df, month, "month")?.drop(OCCUPIED_NIGHTS)?;
+    let by_month = registry_listings(by_month, "month", |_| {
+        registry
+            .map(|counts| counts.total)
+            .filter(|total| *total > 0)
+    })?;
+    let months = text_column(&by_month, "month")?;
+    let currencies = text_column(&by_month, "currency")?;
+    let nights = nights_by_month(&stays, &text_column(df, amount_columns(df).0)?);
+    let by_month = with_occupancy(
+        by_month,
+        |row| {
+            let key = (
+                months[row].clone().unwrap_or_default(),
+                currencies[row].clone().unwrap_or_default(),
+            );
+            nights.get(&key).copied().unwrap_or_default()
+        },
+        |row| {
+            let (first, last) = month_bounds(months[row].as_deref()?)?;
+            Some((last - first).num_days() + 1)
+        },
+    )?;
+    summaries.push(Summary {
+        sheet: "By month".to_string(),
+        df: by_month,
+    });
+    Ok(summaries)
+}
@@ -0,0 +1,17 @@
+//! Airbnb hosting reservations scraper.
+//!
+//! The executable is a thin wrapper around [`pipeline`]; every stage takes an
+//! explicit [`Config`] so other tools can reuse the [`Reservation`] model and
+//! the commission logic directly.
+pub mod config;
+pub mod error;
+pub mod helpers;
+pub mod models;
+pub mod pipeline;
+pub mod secrets;
+pub mod sources;
+pub mod store;
+
+pub use config::Config;
+pub use error::{Error, Result};
+pub use models::reservation::Reservation;
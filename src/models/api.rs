@@ -0,0 +1,412 @@
+//! Response types of the `reservations` API (`_format=for_remy`).
+//!
+//! Every field is optional so a reservation missing some of them still maps,
+//! and fields the model does not know about are kept in `extra` so schema
+//! drift can be reported instead of silently ignored.
+use super::reservation::{PayoutBreakdown, Reservation};
+use crate::helpers::dates::API_DATE_FORMAT;
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReservationsPage {
+    #[serde(default)]
+    pub reservations: Vec<ApiReservation>,
+    pub metadata: Option<PageMetadata>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PageMetadata {
+    #[serde(default, deserialize_with = "count")]
+    pub total_count: Option<Count>,
+    #[serde(default, deserialize_with = "count")]
+    pub page_count: Option<Count>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A text field sent as a string or, like IDs and times depending on the
+/// endpoint version, as a number; any other value is kept so the
+/// [`SchemaReport`] can flag it instead of failing the whole page.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Text {
+    Value(String),
+    Invalid(Value),
+}
+
+impl Text {
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            Text::Value(value) => Some(value),
+            Text::Invalid(_) => None,
+        }
+    }
+}
+
+fn text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Text>, D::Error> {
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => None,
+        Some(Value::String(value)) => Some(Text::Value(value)),
+        Some(Value::Number(value)) => Some(Text::Value(value.to_string())),
+        Some(other) => Some(Text::Invalid(other)),
+    })
+}
+
+/// `value` as a string, empty when missing or invalid.
+fn text_value(value: Option<Text>) -> String {
+    match value {
+        Some(Text::Value(value)) => value,
+        _ => String::new(),
+    }
+}
+
+/// A count sent as a number or a numeric string; any other value is kept so
+/// the [`SchemaReport`] can flag it instead of failing the whole page.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Count {
+    Number(f64),
+    Invalid(Value),
+}
+
+impl Count {
+    pub fn number(&self) -> Option<f64> {
+        match self {
+            Count::Number(value) => Some(*value),
+            Count::Invalid(_) => None,
+        }
+    }
+}
+
+fn count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Count>, D::Error> {
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => None,
+        Some(Value::Number(value)) => value.as_f64().map(Count::Number),
+        Some(Value::String(value)) => Some(match value.trim().parse::<f64>() {
+            Ok(number) if number.is_finite() => Count::Number(number),
+            _ => Count::Invalid(Value::String(value)),
+        }),
+        Some(other) => Some(Count::Invalid(other)),
+    })
+}
+
+/// `value` as a number, 0 when missing or invalid.
+fn count_value(value: Option<Count>) -> f64 {
+    value.and_then(|count| count.number()).unwrap_or_default()
+}
+
+/// How a record sent one of the fields the model reads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sent {
+    Missing,
+    Invalid,
+    Valid,
+}
+
+impl From<&Option<Text>> for Sent {
+    fn from(value: &Option<Text>) -> Self {
+        match value {
+            None => Sent::Missing,
+            Some(Text::Invalid(_)) => Sent::Invalid,
+            Some(Text::Value(_)) => Sent::Valid,
+        }
+    }
+}
+
+impl From<&Option<Count>> for Sent {
+    fn from(value: &Option<Count>) -> Self {
+        match value {
+            None => Sent::Missing,
+            Some(Count::Invalid(_)) => Sent::Invalid,
+            Some(Count::Number(_)) => Sent::Valid,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct GuestUser {
+    #[serde(default, deserialize_with = "text")]
+    pub id: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub full_name: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub phone: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub country: Option<Text>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct GuestDetails {
+    #[serde(default, deserialize_with = "count")]
+    pub number_of_adults: Option<Count>,
+    #[serde(default, deserialize_with = "count")]
+    pub number_of_children: Option<Count>,
+    #[serde(default, deserialize_with = "count")]
+    pub number_of_infants: Option<Count>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ApiPayoutBreakdown {
+    #[serde(default, deserialize_with = "text")]
+    pub accommodation: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub cleaning_fee: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub service_fee: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub taxes: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub total: Option<Text>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ApiReservation {
+    #[serde(default, deserialize_with = "text")]
+    pub confirmation_code: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub user_facing_status_localized: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub thread_id: Option<Text>,
+    pub guest_user: Option<GuestUser>,
+    pub guest_details: Option<GuestDetails>,
+    #[serde(default, deserialize_with = "text")]
+    pub start_date: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub end_date: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub check_in_time: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub check_out_time: Option<Text>,
+    #[serde(default, deserialize_with = "count")]
+    pub nights: Option<Count>,
+    #[serde(default, deserialize_with = "text")]
+    pub booked_date: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub listing_id: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub listing_name: Option<Text>,
+    #[serde(default, deserialize_with = "text")]
+    pub earnings: Option<Text>,
+    pub payout_breakdown: Option<ApiPayoutBreakdown>,
+    #[serde(default, deserialize_with = "text")]
+    pub cancellation_policy: Option<Text>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Unknown, missing and invalid fields over every page of a fetch.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct SchemaReport {
+    pub records: usize,
+    /// Fields the payload sent that the model does not read, with the number
+    /// of records (or pages) they appeared in.
+    pub unknown_fields: BTreeMap<String, usize>,
+    /// Expected fields that were absent or null, with the number of records.
+    pub missing_fields: BTreeMap<String, usize>,
+    /// Fields sent with a value of the wrong type (read as empty or 0), with
+    /// the number of records (or pages).
+    pub invalid_fields: BTreeMap<String, usize>,
+}
+
+impl SchemaReport {
+    pub fn is_empty(&self) -> bool {
+        self.unknown_fields.is_empty()
+            && self.missing_fields.is_empty()
+            && self.invalid_fields.is_empty()
+    }
+
+    fn unknown(&mut self, prefix: &str, extra: &Map<String, Value>) {
+        for key in extra.keys() {
+            *self
+                .unknown_fields
+                .entry(format!("{}{}", prefix, key))
+                .or_default() += 1;
+        }
+    }
+
+    fn missing(&mut self, field: &str, present: bool) {
+        if !present {
+            *self.missing_fields.entry(field.to_string()).or_default() += 1;
+        }
+    }
+
+    fn field(&mut self, field: &str, sent: Sent) {
+        self.missing(field, sent != Sent::Missing);
+        if sent == Sent::Invalid {
+            *self.invalid_fields.entry(field.to_string()).or_default() += 1;
+        }
+    }
+
+    /// Adds the fields of `page` to the report.
+    pub fn record(&mut self, page: &ReservationsPage) {
+        self.unknown("", &page.extra);
+        match &page.metadata {
+            Some(metadata) => {
+                self.unknown("metadata.", &metadata.extra);
+                self.field("metadata.total_count", Sent::from(&metadata.total_count));
+                self.field("metadata.page_count", Sent::from(&metadata.page_count));
+            }
+            None => self.missing("metadata", false),
+        }
+
+        for reservation in &page.reservations {
+            self.records += 1;
+            self.unknown("reservations.", &reservation.extra);
+            for (field, sent) in [
+                (
+                    "confirmation_code",
+                    Sent::from(&reservation.confirmation_code),
+                ),
+                (
+                    "user_facing_status_localized",
+                    Sent::from(&reservation.user_facing_status_localized),
+                ),
+                ("thread_id", Sent::from(&reservation.thread_id)),
+                ("start_date", Sent::from(&reservation.start_date)),
+                ("end_date", Sent::from(&reservation.end_date)),
+                ("check_in_time", Sent::from(&reservation.check_in_time)),
+                ("check_out_time", Sent::from(&reservation.check_out_time)),
+                ("nights", Sent::from(&reservation.nights)),
+                ("booked_date", Sent::from(&reservation.booked_date)),
+                ("listing_id", Sent::from(&reservation.listing_id)),
+                ("listing_name", Sent::from(&reservation.listing_name)),
+                ("earnings", Sent::from(&reservation.earnings)),
+                (
+                    "cancellation_policy",
+                    Sent::from(&reservation.cancellation_policy),
+                ),
+            ] {
+                self.field(&format!("reservations.{}", field), sent);
+            }
+
+            match &reservation.guest_user {
+                Some(guest_user) => {
+                    self.unknown("reservations.guest_user.", &guest_user.extra);
+                    for (field, sent) in [
+                        ("id", Sent::from(&guest_user.id)),
+                        ("full_name", Sent::from(&guest_user.full_name)),
+                        ("phone", Sent::from(&guest_user.phone)),
+                        ("country", Sent::from(&guest_user.country)),
+                    ] {
+                        self.field(&format!("reservations.guest_user.{}", field), sent);
+                    }
+                }
+                None => self.missing("reservations.guest_user", false),
+            }
+            match &reservation.guest_details {
+                Some(guest_details) => {
+                    self.unknown("reservations.guest_details.", &guest_details.extra);
+                    for (field, sent) in [
+                        (
+                            "number_of_adults",
+                            Sent::from(&guest_details.number_of_adults),
+                        ),
+                        (
+                            "number_of_children",
+                            Sent::from(&guest_details.number_of_children),
+                        ),
+                        (
+                            "number_of_infants",
+                            Sent::from(&guest_details.number_of_infants),
+                        ),
+                    ] {
+                        self.field(&format!("reservations.guest_details.{}", field), sent);
+                    }
+                }
+                None => self.missing("reservations.guest_details", false),
+            }
+            match &reservation.payout_breakdown {
+                Some(payout) => {
+                    self.unknown("reservations.payout_breakdown.", &payout.extra);
+                    for (field, sent) in [
+                        ("accommodation", Sent::from(&payout.accommodation)),
+                        ("cleaning_fee", Sent::from(&payout.cleaning_fee)),
+                        ("service_fee", Sent::from(&payout.service_fee)),
+                        ("taxes", Sent::from(&payout.taxes)),
+                        ("total", Sent::from(&payout.total)),
+                    ] {
+                        self.field(&format!("reservations.payout_breakdown.{}", field), sent);
+                    }
+                }
+                None => self.missing("reservations.payout_breakdown", false),
+            }
+        }
+    }
+}
+
+/// `value` as a date, or `None` with the raw value added to `invalid_dates`.
+fn date_field(
+    key: &str,
+    value: Option<&Text>,
+    invalid_dates: &mut Vec<String>,
+) -> Option<NaiveDate> {
+    let date = value
+        .and_then(Text::value)
+        .and_then(|date| NaiveDate::parse_from_str(date, API_DATE_FORMAT).ok());
+    if date.is_none() {
+        match value {
+            Some(Text::Value(value)) => invalid_dates.push(format!("{}: {:?}", key, value)),
+            Some(Text::Invalid(value)) => invalid_dates.push(format!("{}: {}", key, value)),
+            None => invalid_dates.push(format!("{}: null", key)),
+        }
+    }
+    date
+}
+
+impl From<ApiReservation> for Reservation {
+    /// Missing or invalid text fields become empty and missing or invalid
+    /// counts become 0; the [`SchemaReport`] is where they show up.
+    fn from(record: ApiReservation) -> Self {
+        let mut invalid_dates: Vec<String> = Vec::new();
+        let start_date = date_field("start_date", record.start_date.as_ref(), &mut invalid_dates);
+        let end_date = date_field("end_date", record.end_date.as_ref(), &mut invalid_dates);
+        let booked_date = date_field(
+            "booked_date",
+            record.booked_date.as_ref(),
+            &mut invalid_dates,
+        );
+        let guest_user = record.guest_user.unwrap_or_default();
+        let guest_details = record.guest_details.unwrap_or_default();
+        let payout = record.payout_breakdown.unwrap_or_default();
+
+        Reservation {
+            confirmation_code: text_value(record.confirmation_code),
+            status: text_value(record.user_facing_status_localized),
+            thread_id: text_value(record.thread_id),
+            guest_user_id: text_value(guest_user.id),
+            guest_user_full_name: text_value(guest_user.full_name),
+            guest_user_phone: text_value(guest_user.phone),
+            guest_user_country: text_value(guest_user.country),
+            guest_details_number_of_adults: count_value(guest_details.number_of_adults),
+            guest_details_number_of_children: count_value(guest_details.number_of_children),
+            guest_details_number_of_infants: count_value(guest_details.number_of_infants),
+            start_date,
+            end_date,
+            check_in_time: text_value(record.check_in_time),
+            check_out_time: text_value(record.check_out_time),
+            nights: count_value(record.nights),
+            booked_date,
+            listing_id: text_value(record.listing_id),
+            listing_name: text_value(record.listing_name),
+            earnings: text_value(record.earnings),
+            payout_breakdown: PayoutBreakdown {
+                accommodation: text_value(payout.accommodation),
+                cleaning_fee: text_value(payout.cleaning_fee),
+                service_fee: text_value(payout.service_fee),
+                taxes: text_value(payout.taxes),
+                total: text_value(payout.total),
+            },
+            cancellation_policy: text_value(record.cancellation_policy),
+            invalid_dates,
+        }
+    }
+}
@@ -1 +1,3 @@
+pub mod api;
+pub mod query;
 pub mod reservation;
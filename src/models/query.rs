@@ -0,0 +1,146 @@
+use crate::error::{Error, Result};
+use crate::helpers::dates::API_DATE_FORMAT;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Values of the `status` filter of the `reservations` API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReservationStatus {
+    Accepted,
+    Request,
+    Pending,
+    Cancelled,
+    Past,
+}
+
+impl ReservationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReservationStatus::Accepted => "accepted",
+            ReservationStatus::Request => "request",
+            ReservationStatus::Pending => "pending",
+            ReservationStatus::Cancelled => "canceled",
+            ReservationStatus::Past => "past",
+        }
+    }
+}
+
+impl fmt::Display for ReservationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ReservationStatus {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "accepted" => Ok(ReservationStatus::Accepted),
+            "request" => Ok(ReservationStatus::Request),
+            "pending" => Ok(ReservationStatus::Pending),
+            "canceled" | "cancelled" => Ok(ReservationStatus::Cancelled),
+            "past" => Ok(ReservationStatus::Past),
+            other => Err(Error::Config(format!(
+                "Unknown reservation status: {} (expected accepted, request, pending, cancelled or past)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Filters and paging of a `reservations` fetch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReservationQuery {
+    pub date_min: String,
+    /// Closes the date window when set; open ended otherwise.
+    pub date_max: Option<String>,
+    pub statuses: Vec<ReservationStatus>,
+    /// Only reservations of these listings; every listing when empty.
+    pub listing_ids: Vec<String>,
+    pub currency: String,
+    pub locale: String,
+    pub page_size: u32,
+}
+
+impl ReservationQuery {
+    /// Accepted reservations and requests from `date_min` on, in PEN and English,
+    /// 40 per page.
+    pub fn new(date_min: &str) -> Self {
+        ReservationQuery {
+            date_min: date_min.to_string(),
+            date_max: None,
+            statuses: vec![ReservationStatus::Accepted, ReservationStatus::Request],
+            listing_ids: Vec::new(),
+            currency: "PEN".to_string(),
+            locale: "en".to_string(),
+            page_size: 40,
+        }
+    }
+
+    /// Inverse of [`to_params`](Self::to_params), e.g. to repeat the query of
+    /// a raw run; `_offset` is ignored.
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self> {
+        let list = |key: &str| -> Vec<&str> {
+            params
+                .get(key)
+                .map(|value| value.split(',').filter(|item| !item.is_empty()).collect())
+                .unwrap_or_default()
+        };
+        let mut query = ReservationQuery::new(
+            params
+                .get("date_min")
+                .ok_or_else(|| Error::parse("query parameters", "date_min is missing"))?,
+        );
+        query.date_max = params.get("date_max").cloned();
+        if params.contains_key("status") {
+            query.statuses = list("status")
+                .into_iter()
+                .map(str::parse)
+                .collect::<Result<_>>()?;
+        }
+        query.listing_ids = list("listing_id").into_iter().map(str::to_string).collect();
+        if let Some(currency) = params.get("currency") {
+            query.currency = currency.clone();
+        }
+        if let Some(locale) = params.get("locale") {
+            query.locale = locale.clone();
+        }
+        if let Some(limit) = params.get("_limit") {
+            query.page_size = limit.parse().map_err(|e| Error::parse("_limit", e))?;
+        }
+        Ok(query)
+    }
+
+    /// First and last day of the window, when it is closed.
+    pub fn period(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let date = |value: &str| NaiveDate::parse_from_str(value, API_DATE_FORMAT).ok();
+        Some((date(&self.date_min)?, date(self.date_max.as_deref()?)?))
+    }
+
+    /// Query parameters of the page starting at `offset`.
+    pub fn to_params(&self, offset: u32) -> HashMap<String, String> {
+        let mut query_params: HashMap<String, String> = HashMap::new();
+        query_params.insert("locale".to_string(), self.locale.clone());
+        query_params.insert("currency".to_string(), self.currency.clone());
+        query_params.insert("_format".to_string(), "for_remy".to_string());
+        query_params.insert("_limit".to_string(), self.page_size.to_string());
+        query_params.insert("_offset".to_string(), offset.to_string());
+        query_params.insert(
+            "collection_strategy".to_string(),
+            "for_reservations_list".to_string(),
+        );
+        query_params.insert("date_min".to_string(), self.date_min.clone());
+        if let Some(date_max) = &self.date_max {
+            query_params.insert("date_max".to_string(), date_max.clone());
+        }
+        let statuses: Vec<&str> = self.statuses.iter().map(|status| status.as_str()).collect();
+        query_params.insert("status".to_string(), statuses.join(","));
+        if !self.listing_ids.is_empty() {
+            query_params.insert("listing_id".to_string(), self.listing_ids.join(","));
+        }
+        query_params
+    }
+}
@@ -1,35 +1,128 @@
-use serde::Serialize;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+/// What the host is paid for a reservation, as formatted by the API
+/// (e.g. `S/ 1,250.50`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PayoutBreakdown {
+    pub accommodation: String,
+    pub cleaning_fee: String,
+    pub service_fee: String,
+    pub taxes: String,
+    pub total: String,
+}
+
+/// Fields added after the first release default to empty, so JSON written by
+/// older versions still reads.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Reservation {
     pub confirmation_code: String,
     pub status: String,
+    #[serde(default)]
+    pub thread_id: String,
+    #[serde(default)]
+    pub guest_user_id: String,
     pub guest_user_full_name: String,
     pub guest_user_phone: String,
+    #[serde(default)]
+    pub guest_user_country: String,
     pub guest_details_number_of_adults: f64,
     pub guest_details_number_of_children: f64,
     pub guest_details_number_of_infants: f64,
-    pub start_date: String,
-    pub end_date: String,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub check_in_time: String,
+    #[serde(default)]
+    pub check_out_time: String,
     pub nights: f64,
-    pub booked_date: String,
+    pub booked_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub listing_id: String,
     pub listing_name: String,
     pub earnings: String,
+    #[serde(default)]
+    pub payout_breakdown: PayoutBreakdown,
+    #[serde(default)]
+    pub cancellation_policy: String,
+    /// Dates the API sent missing or in an unexpected format, as `field: value`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invalid_dates: Vec<String>,
 }
 
+/// Columns of the reservations frame with their workbook headers, in frame order.
+pub const RESERVATION_COLUMNS: [(&str, &str); 26] = [
+    ("confirmation_code", "Confirmation code"),
+    ("status", "Status"),
+    ("thread_id", "Thread ID"),
+    ("guest_user_id", "Guest ID"),
+    ("guest_user_full_name", "Guest name"),
+    ("guest_user_phone", "Contact"),
+    ("guest_user_country", "Guest country"),
+    ("guest_details_number_of_adults", "# of adults"),
+    ("guest_details_number_of_children", "# of children"),
+    ("guest_details_number_of_infants", "# of infants"),
+    ("start_date", "Start date"),
+    ("end_date", "End date"),
+    ("check_in_time", "Check-in time"),
+    ("check_out_time", "Check-out time"),
+    ("nights", "# of nights"),
+    ("booked_date", "Booked"),
+    ("listing_id", "Listing ID"),
+    ("listing_name", "Listing"),
+    ("earnings", "Earnings"),
+    ("payout_breakdown_accommodation", "Accommodation"),
+    ("payout_breakdown_cleaning_fee", "Cleaning fee"),
+    ("payout_breakdown_service_fee", "Service fee"),
+    ("payout_breakdown_taxes", "Taxes"),
+    ("payout_breakdown_total", "Payout"),
+    ("cancellation_policy", "Cancellation policy"),
+    ("invalid_dates", "Invalid dates"),
+];
+
+/// Columns exported when `EXPORT_COLUMNS` is not set.
+pub const DEFAULT_EXPORT_COLUMNS: [&str; 13] = [
+    "confirmation_code",
+    "status",
+    "guest_user_full_name",
+    "guest_user_phone",
+    "guest_details_number_of_adults",
+    "guest_details_number_of_children",
+    "guest_details_number_of_infants",
+    "start_date",
+    "end_date",
+    "nights",
+    "booked_date",
+    "listing_name",
+    "earnings",
+];
+
 #[derive(Debug, Default)]
 pub struct ReservationTable {
     pub confirmation_code: Vec<String>,
     pub status: Vec<String>,
+    pub thread_id: Vec<String>,
+    pub guest_user_id: Vec<String>,
     pub guest_user_full_name: Vec<String>,
     pub guest_user_phone: Vec<String>,
+    pub guest_user_country: Vec<String>,
     pub guest_details_number_of_adults: Vec<f64>,
     pub guest_details_number_of_children: Vec<f64>,
     pub guest_details_number_of_infants: Vec<f64>,
-    pub start_date: Vec<String>,
-    pub end_date: Vec<String>,
+    pub start_date: Vec<Option<NaiveDate>>,
+    pub end_date: Vec<Option<NaiveDate>>,
+    pub check_in_time: Vec<String>,
+    pub check_out_time: Vec<String>,
     pub nights: Vec<f64>,
-    pub booked_date: Vec<String>,
+    pub booked_date: Vec<Option<NaiveDate>>,
+    pub listing_id: Vec<String>,
     pub listing_name: Vec<String>,
     pub earnings: Vec<String>,
+    pub payout_breakdown_accommodation: Vec<String>,
+    pub payout_breakdown_cleaning_fee: Vec<String>,
+    pub payout_breakdown_service_fee: Vec<String>,
+    pub payout_breakdown_taxes: Vec<String>,
+    pub payout_breakdown_total: Vec<String>,
+    pub cancellation_policy: Vec<String>,
+    pub invalid_dates: Vec<Option<String>>,
 }
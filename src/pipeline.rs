@@ -0,0 +1,310 @@
+//! The stages run by the executable, usable on their own:
+//! fetch → transform → commission → validate → summarize → export, plus the owner statements,
+//! the incremental sync and the diff between snapshots.
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::helpers::diff::{diff_reservations, ReservationDiff};
+use crate::helpers::engine::{
+    fetch_reservations, get_consolidated_data, get_dataframe, get_listing_counts,
+    get_mapping_issues, process_data, select_columns,
+};
+use crate::helpers::excel::{
+    write_diff_to_excel_file, write_report_to_excel_file, write_statement_to_excel_file,
+    write_to_excel_file_refac,
+};
+use crate::helpers::fx::{self, RateProvider, RateTable};
+use crate::helpers::mapping::{row_issues, Issue};
+use crate::helpers::statement::{self, Statement};
+use crate::helpers::summary::{self, Summary};
+use crate::models::api::SchemaReport;
+use crate::models::query::ReservationQuery;
+use crate::models::reservation::Reservation;
+use crate::secrets;
+use crate::sources::airbnb::AirbnbSource;
+use crate::sources::recording::{RecordingSource, RunManifest};
+use crate::sources::replay::ReplaySource;
+use crate::sources::ReservationSource;
+use crate::store::{Store, SyncRecord};
+use chrono::{NaiveDate, Utc};
+use polars::prelude::DataFrame;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Saved pages in `replay` when given, otherwise the live API with the
+/// credentials of the configured secrets backend.
+pub fn open_source(config: &Config, replay: Option<&Path>) -> Result<Box<dyn ReservationSource>> {
+    match replay {
+        Some(dir) => Ok(Box::new(ReplaySource::new(dir)?)),
+        None => Ok(Box::new(AirbnbSource::new(
+            config,
+            secrets::from_config(config)?,
+        )?)),
+    }
+}
+
+/// Wraps `source` so every page it returns is saved, gzipped, in a new
+/// `{prefix1}_raw_{suffix}` run directory with a manifest of the requests.
+pub fn record(
+    config: &Config,
+    source: Box<dyn ReservationSource>,
+    output_dir: &Path,
+) -> Result<RecordingSource> {
+    let dir = output_dir.join(format!(
+        "{}_raw_{}",
+        config.output_file_prefix_1, config.datetime_suffix
+    ));
+    RecordingSource::new(source, &dir)
+}
+
+/// The query a raw run directory was fetched with.
+pub fn recorded_query(dir: &Path) -> Result<ReservationQuery> {
+    let params: HashMap<String, String> = RunManifest::read(dir)?.query.into_iter().collect();
+    ReservationQuery::from_params(&params)
+}
+
+/// Every reservation matching `query`, across all its pages, with the
+/// unknown and missing fields of the payload.
+pub fn fetch(
+    source: &mut dyn ReservationSource,
+    query: &ReservationQuery,
+) -> Result<(Vec<Reservation>, SchemaReport)> {
+    fetch_reservations(source, query)
+}
+
+/// Writes the schema drift report as JSON and returns its path.
+pub fn export_schema_report(
+    config: &Config,
+    report: &SchemaReport,
+    output_dir: &Path,
+) -> Result<PathBuf> {
+    let prefix = format!("{}_schema", config.output_file_prefix_1);
+    let path = config.output_path(output_dir, &prefix, "json");
+    let file = File::create(&path)
+        .map_err(|e| Error::io(format!("Could not create {}", path.display()), e))?;
+    serde_json::to_writer_pretty(file, report)
+        .map_err(|e| Error::io("Could not write the schema report", e.into()))?;
+    Ok(path)
+}
+
+/// Reservations as a DataFrame with `earnings` split into the ISO `currency`
+/// and the `amount`; amounts without a symbol are in the configured currency.
+pub fn transform(config: &Config, reservations: Vec<Reservation>) -> Result<DataFrame> {
+    process_data(get_dataframe(reservations)?, &config.query_currency)
+}
+
+/// Joins the listings and owners of the base workbook and applies the
+/// commission rules, adding `Comision`, `commission_rule` and
+/// `commission_earnings`.
+pub fn commission(config: &Config, df: DataFrame) -> Result<DataFrame> {
+    get_consolidated_data(config, df)
+}
+
+/// The rate table of `FX_RATES`.
+pub fn rate_table(config: &Config) -> Result<RateTable> {
+    let path = config
+        .fx_rates
+        .as_deref()
+        .ok_or_else(|| Error::Config("FX_RATES is not set".to_string()))?;
+    RateTable::open(Path::new(path), &config.fx_rates_sheet)
+}
+
+/// Adds the amounts and commissions converted into the reporting currency
+/// with the rates of `provider`; `df` is returned as is when no reporting
+/// currency is configured.
+pub fn convert(config: &Config, df: DataFrame, provider: &dyn RateProvider) -> Result<DataFrame> {
+    match &config.reporting_currency {
+        Some(currency) => fx::convert(df, provider, currency, config.commission_rounding),
+        None => Ok(df),
+    }
+}
+
+/// Writes the raw reservations workbook with the `export_columns` and the
+/// invalid dates, and returns its path.
+pub fn export_reservations(
+    config: &Config,
+    reservations: &[Reservation],
+    output_dir: &Path,
+) -> Result<PathBuf> {
+    let path = config.output_path(output_dir, &config.output_file_prefix_1, "xlsx");
+    let mut columns = config.export_columns.clone();
+    if !columns.iter().any(|column| column == "invalid_dates") {
+        columns.push("invalid_dates".to_string());
+    }
+    let df = select_columns(get_dataframe(reservations.to_vec())?, &columns)?;
+    write_to_excel_file_refac(&path.to_string_lossy(), df)?;
+    Ok(path)
+}
+
+/// Issues of the listing and owner sheets of the base workbook with the
+/// reservations of `df`, followed by the reservations a processing stage
+/// could not price. With `strict_mapping` any issue of the base workbook is
+/// an error.
+pub fn validate(config: &Config, df: &DataFrame) -> Result<Vec<Issue>> {
+    let mut issues = get_mapping_issues(config, df)?;
+    if config.strict_mapping && !issues.is_empty() {
+        let issues: Vec<String> = issues
+            .iter()
+            .map(|issue| format!("{} {}: {}", issue.kind.label(), issue.key, issue.detail))
+            .collect();
+        return Err(Error::mapping_sheet(
+            &config.first_sheet,
+            format!("{} issues: {}", issues.len(), issues.join("; ")),
+        ));
+    }
+    issues.extend(row_issues(df)?);
+    Ok(issues)
+}
+
+/// Totals of the consolidated reservations by listing, owner, zone and month;
+/// occupancy is measured against `period` when the fetch window is closed,
+/// and against every listing of the base workbook.
+pub fn summarize(
+    config: &Config,
+    df: &DataFrame,
+    period: Option<(NaiveDate, NaiveDate)>,
+) -> Result<Vec<Summary>> {
+    let listings = get_listing_counts(config)?;
+    summary::summarize(df, &config.zone_column, period, Some(&listings))
+}
+
+/// Writes the consolidated workbook with the `export_columns`, followed by a
+/// sheet per summary and the `Issues` sheet, and returns its path.
+pub fn export(
+    config: &Config,
+    df: DataFrame,
+    summaries: &[Summary],
+    issues: &[Issue],
+    output_dir: &Path,
+) -> Result<PathBuf> {
+    let path = config.output_path(output_dir, &config.output_file_prefix_2, "xlsx");
+    let df = select_columns(df, &config.export_columns)?;
+    write_report_to_excel_file(&path.to_string_lossy(), df, summaries, issues)?;
+    Ok(path)
+}
+
+/// One statement per owner of the consolidated reservations; `period` is
+/// the one of every statement when given, and only the reservations whose
+/// `COMMISSION_DATE` falls in it are included.
+pub fn statements(
+    config: &Config,
+    df: &DataFrame,
+    period: Option<(NaiveDate, NaiveDate)>,
+) -> Result<Vec<Statement>> {
+    let listings = get_listing_counts(config)?;
+    statement::statements(
+        df,
+        &config.zone_column,
+        config.commission_date,
+        period,
+        Some(&listings),
+    )
+}
+
+/// `path`, or `path` with `-2`, `-3`… appended to its file stem when one of
+/// the `taken` paths already has that name, ignoring case.
+fn unique_path(path: PathBuf, taken: &mut HashSet<String>) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut unique = path.clone();
+    let mut n = 1;
+    while !taken.insert(unique.to_string_lossy().to_lowercase()) {
+        n += 1;
+        unique = path.with_file_name(format!("{}-{}.xlsx", stem, n));
+    }
+    unique
+}
+
+/// Writes a workbook per statement, named after `statement_file_pattern`,
+/// with the `export_columns` of the reservations, and returns their paths.
+/// Owners whose file names end up the same once sanitized (`A/B` and `A_B`)
+/// get a `-2`, `-3`… suffix in statement order instead of overwriting each
+/// other.
+pub fn export_statements(
+    config: &Config,
+    statements: &[Statement],
+    output_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::with_capacity(statements.len());
+    let mut taken = HashSet::new();
+    for statement in statements {
+        let path = unique_path(
+            config.statement_path(output_dir, &statement.owner, statement.period),
+            &mut taken,
+        );
+        let statement = Statement {
+            reservations: select_columns(statement.reservations.clone(), &config.export_columns)?,
+            ..statement.clone()
+        };
+        write_statement_to_excel_file(&path.to_string_lossy(), &statement)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// New, cancelled, modified and disappeared reservations of `current`
+/// against the `previous` snapshot.
+pub fn diff(previous: &[Reservation], current: &[Reservation]) -> ReservationDiff {
+    diff_reservations(previous, current)
+}
+
+/// Writes the diff as a `Changes` workbook and as JSON, returning both paths.
+pub fn export_diff(
+    config: &Config,
+    diff: &ReservationDiff,
+    output_dir: &Path,
+) -> Result<(PathBuf, PathBuf)> {
+    let prefix = format!("{}_diff", config.output_file_prefix_1);
+    let xlsx_path = config.output_path(output_dir, &prefix, "xlsx");
+    write_diff_to_excel_file(&xlsx_path.to_string_lossy(), diff)?;
+
+    let json_path = config.output_path(output_dir, &prefix, "json");
+    let file = File::create(&json_path)
+        .map_err(|e| Error::io(format!("Could not create {}", json_path.display()), e))?;
+    serde_json::to_writer_pretty(file, diff)
+        .map_err(|e| Error::io("Could not write the diff", e.into()))?;
+    Ok((xlsx_path, json_path))
+}
+
+/// Fetches what changed since the last sync recorded in `store`, upserts it
+/// and saves the store. `full` ignores the previous syncs and refetches
+/// everything from `query.date_min`.
+pub fn sync(
+    config: &Config,
+    source: &mut dyn ReservationSource,
+    store: &mut Store,
+    query: &ReservationQuery,
+    full: bool,
+) -> Result<(SyncRecord, SchemaReport)> {
+    let date_min = query.date_min.as_str();
+    let date_max = query.date_max.as_deref();
+    let fetched_from = if full {
+        date_min.to_string()
+    } else {
+        store.sync_date_min(date_min, date_max, config.sync_lookback_days)?
+    };
+    let (reservations, schema) = fetch(
+        source,
+        &ReservationQuery {
+            date_min: fetched_from.clone(),
+            ..query.clone()
+        },
+    )?;
+
+    let now = Utc::now();
+    let counts = store.upsert(reservations, now);
+    let record = SyncRecord {
+        finished_at: now,
+        date_min: date_min.to_string(),
+        date_max: date_max.map(str::to_string),
+        fetched_from,
+        new: counts.new,
+        updated: counts.updated,
+        unchanged: counts.unchanged,
+    };
+    store.record_sync(record.clone());
+    store.save()?;
+    Ok((record, schema))
+}
@@ -0,0 +1,23 @@
+use super::SecretsProvider;
+use crate::error::{Error, Result};
+use std::env;
+
+/// Reads each secret from a `SECRET_<KEY>` environment variable
+/// (leading underscores dropped, so `_AAT` is `SECRET_AAT`).
+pub struct EnvSecrets;
+
+impl EnvSecrets {
+    pub fn var_name(key: &str) -> String {
+        format!("SECRET_{}", key.trim_start_matches('_'))
+    }
+}
+
+impl SecretsProvider for EnvSecrets {
+    fn get(&self, key: &str) -> Result<String> {
+        let name = EnvSecrets::var_name(key);
+        env::var(&name).map_err(|_| Error::Secrets {
+            key: key.to_string(),
+            message: format!("{} is not set", name),
+        })
+    }
+}
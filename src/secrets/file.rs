@@ -0,0 +1,55 @@
+use super::SecretsProvider;
+use crate::error::{Error, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Flat TOML or JSON file (picked by extension) mapping keys to values.
+pub struct FileSecrets {
+    path: String,
+    values: HashMap<String, String>,
+}
+
+impl FileSecrets {
+    pub fn new(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| Error::io(format!("Could not read secrets file {}", path), e))?;
+        let table: HashMap<String, Value> =
+            match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+                Some("json") => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+                _ => toml::from_str(&contents).map_err(|e| e.to_string()),
+            }
+            .map_err(|message| Error::Secrets {
+                key: path.to_string(),
+                message,
+            })?;
+
+        let values = table
+            .into_iter()
+            .map(|(key, value)| match value {
+                Value::String(s) => (key, s),
+                other => (key, other.to_string()),
+            })
+            .collect();
+
+        Ok(FileSecrets {
+            path: path.to_string(),
+            values,
+        })
+    }
+}
+
+impl SecretsProvider for FileSecrets {
+    fn get(&self, key: &str) -> Result<String> {
+        self.values.get(key).cloned().ok_or_else(|| Error::Secrets {
+            key: key.to_string(),
+            message: format!("not set in {}", self.path),
+        })
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        *self = FileSecrets::new(&self.path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+pub mod env;
+pub mod file;
+pub mod ssm;
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+
+/// Flag that must be `true` for the scraper to run.
+pub const ENABLE: &str = "ENABLE";
+/// Airbnb session token sent in the `_aat` cookie.
+pub const AAT: &str = "_AAT";
+/// Airbnb public API key sent in the `x-airbnb-api-key` header.
+pub const AIRBNB_API_KEY: &str = "AIRBNB_API_KEY";
+
+/// Backend holding the Airbnb credentials.
+pub trait SecretsProvider {
+    /// Returns the value stored under `key`, failing when it is missing.
+    fn get(&self, key: &str) -> Result<String>;
+
+    /// Drops cached values so the next `get` sees rotated secrets.
+    fn reload(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Builds the backend selected by `SECRETS_BACKEND` (`env`, `file` or `ssm`).
+pub fn from_config(config: &Config) -> Result<Box<dyn SecretsProvider>> {
+    match config.secrets_backend.as_str() {
+        "env" => Ok(Box::new(env::EnvSecrets)),
+        "file" => {
+            let path = config.secrets_file.as_deref().ok_or_else(|| {
+                Error::Config("SECRETS_FILE must be set for the file secrets backend".into())
+            })?;
+            Ok(Box::new(file::FileSecrets::new(path)?))
+        }
+        "ssm" => {
+            let prefix = config.ssm_prefix.as_deref().ok_or_else(|| {
+                Error::Config("SSM_PREFIX must be set for the ssm secrets backend".into())
+            })?;
+            Ok(Box::new(ssm::SsmSecrets::new(
+                prefix,
+                config.ssm_region.as_deref(),
+                config.ssm_profile.as_deref(),
+            )?))
+        }
+        backend => Err(Error::Config(format!(
+            "Unknown secrets backend: {}",
+            backend
+        ))),
+    }
+}
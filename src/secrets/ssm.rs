@@ -0,0 +1,87 @@
+use super::SecretsProvider;
+use crate::error::{Error, Result};
+use aws_config::BehaviorVersion;
+use aws_sdk_ssm::error::DisplayErrorContext;
+use aws_sdk_ssm::{config::Region, Client as ssm_client};
+use std::collections::HashMap;
+use tokio::runtime::Runtime;
+
+/// AWS SSM parameters under a path prefix, resolved with the default AWS
+/// credential chain (env vars, shared profile, SSO, instance role, ...).
+pub struct SsmSecrets {
+    prefix: String,
+    region: Option<String>,
+    profile: Option<String>,
+    params: HashMap<String, String>,
+}
+
+impl SsmSecrets {
+    pub fn new(prefix: &str, region: Option<&str>, profile: Option<&str>) -> Result<Self> {
+        let mut secrets = SsmSecrets {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            region: region.map(str::to_string),
+            profile: profile.map(str::to_string),
+            params: HashMap::new(),
+        };
+        secrets.reload()?;
+        Ok(secrets)
+    }
+
+    fn load_params(&self) -> Result<HashMap<String, String>> {
+        let runtime = Runtime::new().map_err(|e| Error::io("Could not start the runtime", e))?;
+        runtime.block_on(async {
+            let mut loader = aws_config::defaults(BehaviorVersion::latest());
+            if let Some(region) = &self.region {
+                loader = loader.region(Region::new(region.clone()));
+            }
+            if let Some(profile) = &self.profile {
+                loader = loader.profile_name(profile);
+            }
+            let client = ssm_client::new(&loader.load().await);
+
+            let mut params: HashMap<String, String> = HashMap::new();
+            let mut next_token: Option<String> = None;
+            loop {
+                let response = client
+                    .get_parameters_by_path()
+                    .path(&self.prefix)
+                    .with_decryption(true)
+                    .set_next_token(next_token)
+                    .send()
+                    .await
+                    .map_err(|e| Error::Secrets {
+                        key: self.prefix.clone(),
+                        message: format!("{}", DisplayErrorContext(e)),
+                    })?;
+                for param in response.parameters.unwrap_or_default() {
+                    if let (Some(name), Some(value)) = (param.name, param.value) {
+                        params.insert(name, value);
+                    }
+                }
+                next_token = response.next_token;
+                if next_token.is_none() {
+                    break;
+                }
+            }
+            Ok(params)
+        })
+    }
+}
+
+impl SecretsProvider for SsmSecrets {
+    fn get(&self, key: &str) -> Result<String> {
+        let name = format!("{}/{}", self.prefix, key);
+        self.params
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| Error::Secrets {
+                key: key.to_string(),
+                message: format!("SSM parameter {} is not set", name),
+            })
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        self.params = self.load_params()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use super::retry::{RateLimiter, RetryPolicy};
+use super::{Attempt, ReservationSource};
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::secrets::{SecretsProvider, AAT, AIRBNB_API_KEY, ENABLE};
+use reqwest::blocking::Client as req_client;
+use reqwest::blocking::Response;
+use reqwest::header;
+use reqwest::{StatusCode, Url};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// How a rejected `_aat` session token gets replaced.
+pub enum AatRefresh {
+    /// Re-read `_AAT` from the secrets backend.
+    Secrets(Box<dyn SecretsProvider>),
+    /// Run a shell command that prints the new token on stdout.
+    Command(String),
+}
+
+/// Live reservations API of the host account.
+pub struct AirbnbSource {
+    client: req_client,
+    endpoint: String,
+    headers: header::HeaderMap,
+    cookies: String,
+    refresh: Option<AatRefresh>,
+    retry: RetryPolicy,
+    limiter: RateLimiter,
+    attempts: Vec<Attempt>,
+}
+
+fn cookie_header(_aat: &str) -> String {
+    format!("country=PE; _aat={}", _aat)
+}
+
+fn run_refresh_command(command: &str) -> Result<String> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .map_err(|e| Error::io("Could not run AAT_REFRESH_COMMAND", e))?;
+    if !output.status.success() {
+        return Err(Error::Secrets {
+            key: AAT.to_string(),
+            message: format!("AAT_REFRESH_COMMAND exited with {}", output.status),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn header_value(key: &str, value: &str) -> Result<header::HeaderValue> {
+    value.parse().map_err(|_| Error::Secrets {
+        key: key.to_string(),
+        message: "not a valid header value".to_string(),
+    })
+}
+
+impl AirbnbSource {
+    /// Reads the credentials from `secrets` and targets `AIRBNB_BASE_URL`.
+    ///
+    /// On 401 the `_aat` token is refreshed with `AAT_REFRESH_COMMAND` when set,
+    /// otherwise it is re-read from `secrets`. Retries, rate limit and timeout
+    /// come from `config` as well.
+    pub fn new(config: &Config, secrets: Box<dyn SecretsProvider>) -> Result<Self> {
+        let _enable: String = secrets.get(ENABLE)?;
+        if _enable != "true" {
+            return Err(Error::ServiceDisabled);
+        }
+
+        let _aat: String = secrets.get(AAT)?;
+        let airbnb_api_key: String = secrets.get(AIRBNB_API_KEY)?;
+
+        let refresh = match &config.aat_refresh_command {
+            Some(command) => AatRefresh::Command(command.clone()),
+            None => AatRefresh::Secrets(secrets),
+        };
+        let retry = RetryPolicy {
+            max_retries: config.max_retries,
+            base_delay: Duration::from_millis(config.retry_base_delay_ms),
+            max_delay: Duration::from_millis(config.retry_max_delay_ms),
+        };
+        AirbnbSource::with_credentials(&config.airbnb_base_url, &airbnb_api_key, &_aat)?
+            .with_refresh(refresh)
+            .with_retry(retry)
+            .with_rate_limit(config.requests_per_second)?
+            .with_timeout(Duration::from_secs(config.request_timeout_secs))
+    }
+
+    /// Targets `base_url` (e.g. `https://www.airbnb.com` or a local mock server).
+    pub fn with_credentials(base_url: &str, airbnb_api_key: &str, _aat: &str) -> Result<Self> {
+        let base_url = base_url.trim_end_matches('/');
+        let url = Url::parse(base_url)
+            .map_err(|e| Error::Config(format!("Invalid Airbnb base URL {}: {}", base_url, e)))?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        // Cookies
+        let cookies = cookie_header(_aat);
+
+        // Headers
+        let mut headers = header::HeaderMap::new();
+        headers.insert("Accept", "*/*".parse().unwrap());
+        headers.insert("Connection", "keep-alive".parse().unwrap());
+        headers.insert("Content-Type", "application/json".parse().unwrap());
+        headers.insert("Host", host.parse().unwrap());
+        headers.insert(
+            "Referer",
+            format!("{}/hosting/reservations", base_url)
+                .parse()
+                .unwrap(),
+        );
+        headers.insert(
+            "x-airbnb-api-key",
+            header_value(AIRBNB_API_KEY, airbnb_api_key)?,
+        );
+        header_value(AAT, _aat)?;
+
+        Ok(AirbnbSource {
+            client: req_client::new(),
+            endpoint: format!("{}/api/v2/reservations", base_url),
+            headers,
+            cookies,
+            refresh: None,
+            retry: RetryPolicy::default(),
+            limiter: RateLimiter::new(0.0)?,
+            attempts: Vec::new(),
+        })
+    }
+
+    pub fn with_refresh(mut self, refresh: AatRefresh) -> Self {
+        self.refresh = Some(refresh);
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, requests_per_second: f64) -> Result<Self> {
+        self.limiter = RateLimiter::new(requests_per_second)?;
+        Ok(self)
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.client = req_client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|source| Error::Http {
+                url: self.endpoint.clone(),
+                source,
+            })?;
+        Ok(self)
+    }
+
+    /// Sends the request, retrying 429/5xx answers and timeouts until the
+    /// retry budget is spent. The last answer is returned as is.
+    fn send(&mut self, query_params: &HashMap<String, String>) -> Result<Response> {
+        let mut attempt: u32 = 0;
+        loop {
+            self.limiter.wait();
+            let result = self
+                .client
+                .get(&self.endpoint)
+                .headers(self.headers.clone())
+                .query(query_params)
+                .header(reqwest::header::COOKIE, self.cookies.clone())
+                .send();
+            self.attempts.push(Attempt {
+                status: result
+                    .as_ref()
+                    .ok()
+                    .map(|response| response.status().as_u16()),
+                error: result.as_ref().err().map(|error| error.to_string()),
+            });
+
+            let delay = match &result {
+                Ok(response) if RetryPolicy::is_retryable(response.status()) => {
+                    println!("Status code: {}", response.status());
+                    self.retry
+                        .server_delay(response.headers())
+                        .unwrap_or_else(|| self.retry.backoff(attempt))
+                }
+                Err(error) if error.is_timeout() || error.is_connect() => {
+                    println!("Request failed: {}", error);
+                    self.retry.backoff(attempt)
+                }
+                _ => return self.checked(result),
+            };
+            if attempt >= self.retry.max_retries {
+                return self.checked(result);
+            }
+            attempt += 1;
+            println!(
+                "Retrying in {:.1}s (attempt {} of {})",
+                delay.as_secs_f64(),
+                attempt,
+                self.retry.max_retries
+            );
+            thread::sleep(delay);
+        }
+    }
+
+    fn checked(&self, result: reqwest::Result<Response>) -> Result<Response> {
+        result.map_err(|source| Error::Http {
+            url: self.endpoint.clone(),
+            source,
+        })
+    }
+
+    fn refresh_aat(&mut self) -> Result<()> {
+        let _aat: String = match self.refresh.as_mut() {
+            Some(AatRefresh::Secrets(secrets)) => {
+                secrets.reload()?;
+                secrets.get(AAT)?
+            }
+            Some(AatRefresh::Command(command)) => run_refresh_command(command)?,
+            None => {
+                return Err(Error::Auth {
+                    credential: AAT.to_string(),
+                    refreshed: false,
+                })
+            }
+        };
+        header_value(AAT, &_aat)?;
+        self.cookies = cookie_header(&_aat);
+        Ok(())
+    }
+}
+
+impl ReservationSource for AirbnbSource {
+    fn fetch_page(&mut self, query_params: &HashMap<String, String>) -> Result<Value> {
+        self.attempts.clear();
+        let mut response = self.send(query_params)?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            println!("Status code: {}, refreshing {}", response.status(), AAT);
+            self.refresh_aat()?;
+            response = self.send(query_params)?;
+        }
+
+        match response.status() {
+            StatusCode::OK => (),
+            StatusCode::CREATED => (),
+            StatusCode::ACCEPTED => (),
+            StatusCode::UNAUTHORIZED => {
+                return Err(Error::Auth {
+                    credential: AAT.to_string(),
+                    refreshed: true,
+                });
+            }
+            status => {
+                return Err(Error::HttpStatus {
+                    url: self.endpoint.clone(),
+                    status,
+                });
+            }
+        }
+
+        response
+            .json()
+            .map_err(|e| Error::Schema(format!("Response is not valid JSON: {}", e)))
+    }
+
+    fn attempts(&self) -> Vec<Attempt> {
+        self.attempts.clone()
+    }
+}
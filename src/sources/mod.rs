@@ -0,0 +1,33 @@
+pub mod airbnb;
+pub mod recording;
+pub mod replay;
+pub mod retry;
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Provider of raw `reservations` API pages.
+///
+/// Implementations only deal with transport; pagination and the mapping to
+/// [`Reservation`](crate::models::reservation::Reservation) live in the engine.
+pub trait ReservationSource {
+    /// Returns the JSON payload (`reservations` + `metadata`) for the given query,
+    /// `_offset` and `_limit` included.
+    fn fetch_page(&mut self, query_params: &HashMap<String, String>) -> Result<Value>;
+
+    /// Requests sent by the last `fetch_page`, retries included; empty for
+    /// sources that do not call the API.
+    fn attempts(&self) -> Vec<Attempt> {
+        Vec::new()
+    }
+}
+
+/// One HTTP request sent for a page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attempt {
+    /// Status code, when the API answered.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
@@ -0,0 +1,162 @@
+use super::{Attempt, ReservationSource};
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Name of the manifest in a raw run directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// One request of a raw run, successful or not.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageRecord {
+    pub page: usize,
+    pub offset: u32,
+    pub query_params: BTreeMap<String, String>,
+    /// HTTP status of the last attempt, when the API answered.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    /// Every request sent for the page, retries included.
+    #[serde(default)]
+    pub attempts: Vec<Attempt>,
+    pub fetched_at: DateTime<Utc>,
+    /// Including the retries of the page.
+    pub elapsed_ms: u64,
+    /// Gzipped payload, relative to the run directory; only for saved pages.
+    pub file: Option<String>,
+}
+
+/// What a raw run fetched, written next to its pages.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub started_at: DateTime<Utc>,
+    /// Query parameters of the first page, without `_offset`.
+    pub query: BTreeMap<String, String>,
+    pub pages: Vec<PageRecord>,
+}
+
+impl RunManifest {
+    pub fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let file = File::open(&path)
+            .map_err(|e| Error::io(format!("Could not open {}", path.display()), e))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| Error::parse(path.display().to_string(), e))
+    }
+}
+
+/// Status code an error was answered with, if the API answered at all.
+fn error_status(error: &Error) -> Option<u16> {
+    match error {
+        Error::HttpStatus { status, .. } => Some(status.as_u16()),
+        Error::Auth { .. } => Some(401),
+        _ => None,
+    }
+}
+
+/// Saves every page fetched through `inner` as gzipped JSON in a run
+/// directory, with a manifest rewritten after each request so an aborted run
+/// still documents what it fetched.
+pub struct RecordingSource {
+    inner: Box<dyn ReservationSource>,
+    dir: PathBuf,
+    manifest: RunManifest,
+}
+
+impl RecordingSource {
+    pub fn new(inner: Box<dyn ReservationSource>, dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .map_err(|e| Error::io(format!("Could not create {}", dir.display()), e))?;
+        Ok(RecordingSource {
+            inner,
+            dir: dir.to_path_buf(),
+            manifest: RunManifest {
+                started_at: Utc::now(),
+                query: BTreeMap::new(),
+                pages: Vec::new(),
+            },
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn save_page(&self, file: &str, data: &Value) -> Result<()> {
+        let path = self.dir.join(file);
+        let write_error = |e| Error::io(format!("Could not write {}", path.display()), e);
+        let file = File::create(&path).map_err(write_error)?;
+        let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+        serde_json::to_writer(&mut encoder, data).map_err(|e| write_error(e.into()))?;
+        encoder
+            .finish()
+            .and_then(|mut writer| writer.flush())
+            .map_err(write_error)
+    }
+
+    fn save_manifest(&self) -> Result<()> {
+        let path = self.dir.join(MANIFEST_FILE);
+        let file = File::create(&path)
+            .map_err(|e| Error::io(format!("Could not create {}", path.display()), e))?;
+        serde_json::to_writer_pretty(file, &self.manifest)
+            .map_err(|e| Error::io("Could not write the raw run manifest", e.into()))
+    }
+}
+
+impl ReservationSource for RecordingSource {
+    fn fetch_page(&mut self, query_params: &HashMap<String, String>) -> Result<Value> {
+        let params: BTreeMap<String, String> = query_params.clone().into_iter().collect();
+        let offset: u32 = params
+            .get("_offset")
+            .map_or(Ok(0), |offset| offset.parse())
+            .map_err(|e| Error::parse("_offset", e))?;
+        if self.manifest.pages.is_empty() {
+            self.manifest.query = params.clone();
+            self.manifest.query.remove("_offset");
+        }
+        let page = self.manifest.pages.len() + 1;
+
+        let fetched_at = Utc::now();
+        let started = Instant::now();
+        let result = self.inner.fetch_page(query_params);
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        let attempts = self.inner.attempts();
+
+        let mut record = PageRecord {
+            page,
+            offset,
+            query_params: params,
+            status: attempts.last().and_then(|attempt| attempt.status),
+            error: None,
+            fetched_at,
+            elapsed_ms,
+            file: None,
+            attempts,
+        };
+        match &result {
+            Ok(data) => {
+                let file = format!("page_{:04}.json.gz", page);
+                self.save_page(&file, data)?;
+                record.file = Some(file);
+            }
+            Err(error) => {
+                record.status = record.status.or_else(|| error_status(error));
+                record.error = Some(error.to_string());
+            }
+        }
+        self.manifest.pages.push(record);
+        self.save_manifest()?;
+        result
+    }
+
+    fn attempts(&self) -> Vec<Attempt> {
+        self.inner.attempts()
+    }
+}
@@ -0,0 +1,88 @@
+use super::recording::{RunManifest, MANIFEST_FILE};
+use super::ReservationSource;
+use crate::error::{Error, Result};
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+fn is_page(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    name != MANIFEST_FILE && (name.ends_with(".json") || name.ends_with(".json.gz"))
+}
+
+/// Saved `reservations` API responses, one JSON file per page.
+///
+/// A raw run directory written by [`RecordingSource`](super::recording::RecordingSource)
+/// serves its pages by the `_offset` recorded in the manifest. Any other
+/// directory serves its `.json` and `.json.gz` files in file name order, so
+/// `page_0001.json`, `page_0002.json`, ... replay the same sequence the live
+/// API returned.
+pub struct ReplaySource {
+    pages: Vec<PathBuf>,
+    by_offset: HashMap<u32, PathBuf>,
+}
+
+impl ReplaySource {
+    pub fn new(dir: &Path) -> Result<Self> {
+        if dir.join(MANIFEST_FILE).exists() {
+            let by_offset = RunManifest::read(dir)?
+                .pages
+                .into_iter()
+                .filter_map(|page| Some((page.offset, dir.join(page.file?))))
+                .collect();
+            return Ok(ReplaySource {
+                pages: Vec::new(),
+                by_offset,
+            });
+        }
+
+        let mut pages: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| Error::io(format!("Could not open {}", dir.display()), e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| is_page(path))
+            .collect();
+        pages.sort();
+
+        Ok(ReplaySource {
+            pages,
+            by_offset: HashMap::new(),
+        })
+    }
+}
+
+impl ReservationSource for ReplaySource {
+    fn fetch_page(&mut self, query_params: &HashMap<String, String>) -> Result<Value> {
+        let offset: usize = query_params
+            .get("_offset")
+            .map_or(Ok(0), |offset| offset.parse())
+            .map_err(|e| Error::parse("_offset", e))?;
+        let limit: usize = query_params
+            .get("_limit")
+            .map_or(Ok(1), |limit| limit.parse())
+            .map_err(|e| Error::parse("_limit", e))?;
+
+        let path = if self.by_offset.is_empty() {
+            self.pages.get(offset / limit.max(1))
+        } else {
+            u32::try_from(offset)
+                .ok()
+                .and_then(|offset| self.by_offset.get(&offset))
+        }
+        .ok_or_else(|| Error::Schema(format!("No saved page for offset {}", offset)))?;
+        let file = File::open(path)
+            .map_err(|e| Error::io(format!("Could not open {}", path.display()), e))?;
+        let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+            Box::new(GzDecoder::new(BufReader::new(file)))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+        serde_json::from_reader(reader)
+            .map_err(|e| Error::Schema(format!("{}: {}", path.display(), e)))
+    }
+}
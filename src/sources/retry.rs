@@ -0,0 +1,100 @@
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Retries for transient failures (429, 5xx, timeouts) with jittered exponential backoff.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// "Full jitter" delay: random in `[0, min(max_delay, base_delay * 2^attempt)]`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let millis = exponential.as_millis() as u64;
+        Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+    }
+
+    /// Server-requested `Retry-After` delay, capped at `max_delay`.
+    pub fn server_delay(&self, headers: &HeaderMap) -> Option<Duration> {
+        retry_after(headers).map(|delay| delay.min(self.max_delay))
+    }
+
+    pub fn is_retryable(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+}
+
+/// Delay requested by a `Retry-After` header, in seconds or as an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// Client-side cap on the number of requests per second.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Option<Duration>,
+    last: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// `requests_per_second <= 0` disables the limit; a rate that is not a
+    /// finite number, or so small the interval overflows, is rejected.
+    pub fn new(requests_per_second: f64) -> Result<Self> {
+        if !requests_per_second.is_finite() {
+            return Err(Error::Config(format!(
+                "Invalid requests per second: {}",
+                requests_per_second
+            )));
+        }
+        let interval = (requests_per_second > 0.0)
+            .then(|| Duration::try_from_secs_f64(1.0 / requests_per_second))
+            .transpose()
+            .map_err(|e| {
+                Error::Config(format!(
+                    "Invalid requests per second {}: {}",
+                    requests_per_second, e
+                ))
+            })?;
+        Ok(RateLimiter {
+            interval,
+            last: None,
+        })
+    }
+
+    /// Blocks until the next request is allowed.
+    pub fn wait(&mut self) {
+        if let (Some(interval), Some(last)) = (self.interval, self.last) {
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                thread::sleep(interval - elapsed);
+            }
+        }
+        self.last = Some(Instant::now());
+    }
+}
@@ -0,0 +1,222 @@
+//! Local JSON-lines store of every reservation seen across runs, keyed by
+//! `confirmation_code`, plus the log of successful syncs.
+use crate::error::{Error, Result};
+use crate::models::reservation::Reservation;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredReservation {
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Last time the API returned different values for this reservation.
+    pub last_changed: DateTime<Utc>,
+    #[serde(flatten)]
+    pub reservation: Reservation,
+}
+
+/// A successful sync: the requested date range, where the fetch actually
+/// started and what it changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncRecord {
+    pub finished_at: DateTime<Utc>,
+    pub date_min: String,
+    pub date_max: Option<String>,
+    pub fetched_from: String,
+    pub new: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UpsertCounts {
+    pub new: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Line {
+    Sync(SyncRecord),
+    Reservation(Box<StoredReservation>),
+}
+
+fn parse_date(what: &str, value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, DATE_FORMAT).map_err(|e| Error::parse(what, e))
+}
+
+pub struct Store {
+    path: PathBuf,
+    reservations: BTreeMap<String, StoredReservation>,
+    syncs: Vec<SyncRecord>,
+}
+
+impl Store {
+    /// Loads the store at `path`; a missing file is an empty store.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut store = Store {
+            path: path.to_path_buf(),
+            reservations: BTreeMap::new(),
+            syncs: Vec::new(),
+        };
+        if !path.exists() {
+            return Ok(store);
+        }
+
+        let file = File::open(path)
+            .map_err(|e| Error::io(format!("Could not open {}", path.display()), e))?;
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line =
+                line.map_err(|e| Error::io(format!("Could not read {}", path.display()), e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let what = format!("{} line {}", path.display(), number + 1);
+            match serde_json::from_str(&line).map_err(|e| Error::parse(what, e))? {
+                Line::Sync(record) => store.syncs.push(record),
+                Line::Reservation(stored) => {
+                    store
+                        .reservations
+                        .insert(stored.reservation.confirmation_code.clone(), *stored);
+                }
+            }
+        }
+        Ok(store)
+    }
+
+    pub fn last_sync(&self) -> Option<&SyncRecord> {
+        self.syncs.last()
+    }
+
+    pub fn syncs(&self) -> &[SyncRecord] {
+        &self.syncs
+    }
+
+    pub fn get(&self, confirmation_code: &str) -> Option<&StoredReservation> {
+        self.reservations.get(confirmation_code)
+    }
+
+    pub fn reservations(&self) -> impl Iterator<Item = &StoredReservation> {
+        self.reservations.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.reservations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reservations.is_empty()
+    }
+
+    /// Where the next fetch has to start to cover `date_min..date_max`.
+    ///
+    /// When a previous sync already covered that range only the last
+    /// `lookback_days` before it are fetched again (to catch late changes),
+    /// never past `date_max`; otherwise everything from `date_min` is.
+    pub fn sync_date_min(
+        &self,
+        date_min: &str,
+        date_max: Option<&str>,
+        lookback_days: i64,
+    ) -> Result<String> {
+        let requested = parse_date("date_min", date_min)?;
+        let Some(last) = self.last_sync() else {
+            return Ok(date_min.to_string());
+        };
+
+        let covered_min = parse_date("stored date_min", &last.date_min)? <= requested;
+        let date_max = date_max
+            .map(|date_max| parse_date("date_max", date_max))
+            .transpose()?;
+        let covered_max = match (&last.date_max, date_max) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(last_max), Some(date_max)) => {
+                date_max <= parse_date("stored date_max", last_max)?
+            }
+        };
+        if !(covered_min && covered_max) {
+            return Ok(date_min.to_string());
+        }
+
+        let since = Duration::try_days(lookback_days)
+            .and_then(|lookback| last.finished_at.date_naive().checked_sub_signed(lookback))
+            .ok_or_else(|| {
+                Error::Config(format!("Invalid sync lookback of {} days", lookback_days))
+            })?;
+        let since = match date_max {
+            Some(date_max) => since.max(requested).min(date_max),
+            None => since.max(requested),
+        };
+        Ok(since.format(DATE_FORMAT).to_string())
+    }
+
+    /// Inserts new reservations and updates the ones whose values changed.
+    pub fn upsert(&mut self, reservations: Vec<Reservation>, now: DateTime<Utc>) -> UpsertCounts {
+        let mut counts = UpsertCounts::default();
+        for reservation in reservations {
+            match self.reservations.get_mut(&reservation.confirmation_code) {
+                Some(stored) => {
+                    stored.last_seen = now;
+                    if stored.reservation == reservation {
+                        counts.unchanged += 1;
+                    } else {
+                        stored.reservation = reservation;
+                        stored.last_changed = now;
+                        counts.updated += 1;
+                    }
+                }
+                None => {
+                    self.reservations.insert(
+                        reservation.confirmation_code.clone(),
+                        StoredReservation {
+                            first_seen: now,
+                            last_seen: now,
+                            last_changed: now,
+                            reservation,
+                        },
+                    );
+                    counts.new += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    pub fn record_sync(&mut self, record: SyncRecord) {
+        self.syncs.push(record);
+    }
+
+    /// Rewrites the file through a temporary sibling so a failed write
+    /// never leaves a truncated store behind.
+    pub fn save(&self) -> Result<()> {
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        let write_error = |e| Error::io(format!("Could not write {}", tmp_path.display()), e);
+
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        let mut writer = BufWriter::new(File::create(&tmp_path).map_err(write_error)?);
+        let syncs = self.syncs.iter().cloned().map(Line::Sync);
+        let reservations = self
+            .reservations
+            .values()
+            .map(|stored| Line::Reservation(Box::new(stored.clone())));
+        for line in syncs.chain(reservations) {
+            serde_json::to_writer(&mut writer, &line).map_err(|e| write_error(e.into()))?;
+            writer.write_all(b"\n").map_err(write_error)?;
+        }
+        writer.flush().map_err(write_error)?;
+        drop(writer);
+
+        fs::rename(&tmp_path, &self.path)
+            .map_err(|e| Error::io(format!("Could not replace {}", self.path.display()), e))
+    }
+}
@@ -0,0 +1,407 @@
+//! End to end runs of the live source against a local mock of the `reservations` API.
+use airbnb_reservations_scraper::error::{Error, Result};
+use airbnb_reservations_scraper::helpers::engine::fetch_reservations;
+use airbnb_reservations_scraper::models::query::{ReservationQuery, ReservationStatus};
+use airbnb_reservations_scraper::secrets::SecretsProvider;
+use airbnb_reservations_scraper::sources::airbnb::{AatRefresh, AirbnbSource};
+use airbnb_reservations_scraper::sources::retry::{RateLimiter, RetryPolicy};
+use mockito::{Matcher, Server, ServerGuard};
+use std::time::{Duration, Instant};
+
+const PAGE_1: &str = include_str!("fixtures/reservations/page_0001.json");
+const PAGE_2: &str = include_str!("fixtures/reservations/page_0002.json");
+
+fn source(server: &ServerGuard) -> AirbnbSource {
+    AirbnbSource::with_credentials(&server.url(), "test-api-key", "test-aat").unwrap()
+}
+
+fn query() -> ReservationQuery {
+    ReservationQuery::new("2024-01-01")
+}
+
+fn page_mock(server: &mut ServerGuard, offset: &str, body: &str) -> mockito::Mock {
+    server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("_offset".into(), offset.into()),
+            Matcher::UrlEncoded("_limit".into(), "40".into()),
+            Matcher::UrlEncoded("date_min".into(), "2024-01-01".into()),
+        ]))
+        .match_header("x-airbnb-api-key", "test-api-key")
+        .match_header("cookie", "country=PE; _aat=test-aat")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(body)
+        .create()
+}
+
+#[test]
+fn fetches_every_page() {
+    let mut server = Server::new();
+    let first = page_mock(&mut server, "0", PAGE_1);
+    let second = page_mock(&mut server, "40", PAGE_2);
+
+    let (reservations, _) = fetch_reservations(&mut source(&server), &query()).unwrap();
+
+    first.assert();
+    second.assert();
+    let codes: Vec<&str> = reservations
+        .iter()
+        .map(|reservation| reservation.confirmation_code.as_str())
+        .collect();
+    assert_eq!(codes, ["HMABC12345", "HMDEF67890", "HMGHI24680"]);
+}
+
+#[test]
+fn maps_records_to_reservations() {
+    let mut server = Server::new();
+    page_mock(&mut server, "0", PAGE_1);
+    page_mock(&mut server, "40", PAGE_2);
+
+    let (reservations, _) = fetch_reservations(&mut source(&server), &query()).unwrap();
+
+    let reservation = &reservations[0];
+    assert_eq!(reservation.status, "Confirmed");
+    assert_eq!(reservation.guest_user_full_name, "Ana Torres");
+    assert_eq!(reservation.guest_user_phone, "+51 999 111 222");
+    assert_eq!(reservation.guest_details_number_of_adults, 2.0);
+    assert_eq!(reservation.guest_details_number_of_children, 1.0);
+    assert_eq!(reservation.guest_details_number_of_infants, 0.0);
+    assert_eq!(reservation.start_date.unwrap().to_string(), "2024-03-01");
+    assert_eq!(reservation.end_date.unwrap().to_string(), "2024-03-04");
+    assert_eq!(reservation.nights, 3.0);
+    assert_eq!(reservation.booked_date.unwrap().to_string(), "2024-02-10");
+    assert_eq!(reservation.listing_name, "Depa Miraflores");
+    assert_eq!(reservation.earnings, "S/ 1,250.50");
+    assert_eq!(reservation.thread_id, "1876543210");
+    assert_eq!(reservation.guest_user_id, "412345678");
+    assert_eq!(reservation.guest_user_country, "Peru");
+    assert_eq!(reservation.check_in_time, "15:00");
+    assert_eq!(reservation.check_out_time, "11:00");
+    assert_eq!(reservation.listing_id, "48215377");
+    assert_eq!(reservation.payout_breakdown.cleaning_fee, "S/ 150.00");
+    assert_eq!(reservation.payout_breakdown.service_fee, "-S/ 37.50");
+    assert_eq!(reservation.payout_breakdown.total, "S/ 1,250.50");
+    assert_eq!(reservation.cancellation_policy, "Flexible");
+}
+
+#[test]
+fn flags_invalid_dates() {
+    let mut server = Server::new();
+    let page = PAGE_2
+        .replace(
+            r#""start_date": "2024-03-10""#,
+            r#""start_date": "10/03/2024""#,
+        )
+        .replace(r#""booked_date": "2024-02-28""#, r#""booked_date": null"#);
+    let mock = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(page.replace(r#""page_count": 2"#, r#""page_count": 1"#))
+        .create();
+
+    let (reservations, _) = fetch_reservations(&mut source(&server), &query()).unwrap();
+
+    mock.assert();
+    let reservation = &reservations[0];
+    assert_eq!(reservation.start_date, None);
+    assert_eq!(reservation.booked_date, None);
+    assert_eq!(reservation.end_date.unwrap().to_string(), "2024-03-15");
+    assert_eq!(
+        reservation.invalid_dates,
+        [r#"start_date: "10/03/2024""#, "booked_date: null"]
+    );
+}
+
+#[test]
+fn reports_schema_drift() {
+    let mut server = Server::new();
+    let page = PAGE_2
+        .replace(r#""page_count": 2"#, r#""page_count": 1"#)
+        .replace(
+            r#""listing_name""#,
+            r#""guest_vat_country": "PE", "listing_name""#,
+        );
+    let mut value: serde_json::Value = serde_json::from_str(&page).unwrap();
+    value["reservations"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("guest_details");
+    server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(value.to_string())
+        .create();
+
+    let (reservations, schema) = fetch_reservations(&mut source(&server), &query()).unwrap();
+
+    assert_eq!(reservations[0].guest_details_number_of_adults, 0.0);
+    assert_eq!(schema.records, 1);
+    assert_eq!(schema.missing_fields["reservations.guest_details"], 1);
+    assert_eq!(schema.unknown_fields["reservations.guest_vat_country"], 1);
+}
+
+#[test]
+fn reads_numeric_strings_and_reports_wrongly_typed_fields() {
+    let mut server = Server::new();
+    let mut value: serde_json::Value = serde_json::from_str(PAGE_2).unwrap();
+    value["reservations"][0]["nights"] = "3".into();
+    value["reservations"][0]["guest_details"]["number_of_adults"] = "two".into();
+    value["reservations"][0]["guest_user"]["full_name"] = serde_json::json!({"first": "María"});
+    value["metadata"]["page_count"] = "one".into();
+    server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(value.to_string())
+        .create();
+
+    let (reservations, schema) = fetch_reservations(&mut source(&server), &query()).unwrap();
+
+    assert_eq!(reservations[0].nights, 3.0);
+    assert_eq!(reservations[0].guest_details_number_of_adults, 0.0);
+    assert_eq!(reservations[0].guest_details_number_of_infants, 1.0);
+    assert_eq!(
+        schema.invalid_fields["reservations.guest_details.number_of_adults"],
+        1
+    );
+    assert!(!schema.invalid_fields.contains_key("reservations.nights"));
+    assert_eq!(reservations[0].guest_user_full_name, "");
+    assert_eq!(
+        schema.invalid_fields["reservations.guest_user.full_name"],
+        1
+    );
+    // A page count that is not a number ends the fetch after the first page
+    assert_eq!(schema.invalid_fields["metadata.page_count"], 1);
+    assert!(!schema.missing_fields.contains_key("metadata.page_count"));
+    assert!(!schema.is_empty());
+}
+
+#[test]
+fn sends_date_max_when_given() {
+    let mut server = Server::new();
+    let mock = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::UrlEncoded("date_max".into(), "2024-01-31".into()))
+        .with_status(200)
+        .with_body(r#"{"reservations": [], "metadata": {"total_count": 0, "page_count": 0}}"#)
+        .create();
+
+    let query = ReservationQuery {
+        date_max: Some("2024-01-31".to_string()),
+        ..query()
+    };
+    let (reservations, _) = fetch_reservations(&mut source(&server), &query).unwrap();
+
+    mock.assert();
+    assert!(reservations.is_empty());
+}
+
+#[test]
+fn sends_query_filters() {
+    let mut server = Server::new();
+    let mock = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("status".into(), "accepted,canceled".into()),
+            Matcher::UrlEncoded("listing_id".into(), "111,222".into()),
+            Matcher::UrlEncoded("currency".into(), "USD".into()),
+            Matcher::UrlEncoded("locale".into(), "es".into()),
+            Matcher::UrlEncoded("_limit".into(), "10".into()),
+        ]))
+        .with_status(200)
+        .with_body(r#"{"reservations": [], "metadata": {"total_count": 0, "page_count": 0}}"#)
+        .create();
+
+    let query = ReservationQuery {
+        statuses: vec![ReservationStatus::Accepted, ReservationStatus::Cancelled],
+        listing_ids: vec!["111".to_string(), "222".to_string()],
+        currency: "USD".to_string(),
+        locale: "es".to_string(),
+        page_size: 10,
+        ..query()
+    };
+    fetch_reservations(&mut source(&server), &query).unwrap();
+
+    mock.assert();
+}
+
+#[test]
+fn fails_on_unauthorized() {
+    let mut server = Server::new();
+    let mock = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(401)
+        .create();
+
+    let error = fetch_reservations(&mut source(&server), &query()).unwrap_err();
+
+    mock.assert();
+    assert!(matches!(
+        error,
+        Error::Auth {
+            refreshed: false,
+            ..
+        }
+    ));
+}
+
+/// Secrets backend handing out a new `_aat` on every reload.
+struct RotatingSecrets {
+    reloads: usize,
+}
+
+impl SecretsProvider for RotatingSecrets {
+    fn get(&self, key: &str) -> Result<String> {
+        Ok(format!("{}-{}", key, self.reloads))
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        self.reloads += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn refreshes_aat_and_retries_on_unauthorized() {
+    let mut server = Server::new();
+    let rejected = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .match_header("cookie", "country=PE; _aat=test-aat")
+        .with_status(401)
+        .expect(1)
+        .create();
+    let accepted = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .match_header("cookie", "country=PE; _aat=_AAT-1")
+        .with_status(200)
+        .with_body(r#"{"reservations": [], "metadata": {"total_count": 0, "page_count": 0}}"#)
+        .create();
+
+    let mut source = source(&server).with_refresh(AatRefresh::Secrets(Box::new(RotatingSecrets {
+        reloads: 0,
+    })));
+    let (reservations, _) = fetch_reservations(&mut source, &query()).unwrap();
+
+    rejected.assert();
+    accepted.assert();
+    assert!(reservations.is_empty());
+}
+
+#[test]
+fn reports_stale_aat_when_refresh_does_not_help() {
+    let mut server = Server::new();
+    let mock = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(401)
+        .expect(2)
+        .create();
+
+    let mut source = source(&server).with_refresh(AatRefresh::Secrets(Box::new(RotatingSecrets {
+        reloads: 0,
+    })));
+    let error = fetch_reservations(&mut source, &query()).unwrap_err();
+
+    mock.assert();
+    assert!(matches!(
+        error,
+        Error::Auth { ref credential, refreshed: true } if credential == "_AAT"
+    ));
+}
+
+fn quick_retries(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_retries,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(5),
+    }
+}
+
+#[test]
+fn retries_transient_errors() {
+    let mut server = Server::new();
+    let bad_gateway = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(502)
+        .expect(1)
+        .create();
+    let throttled = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(429)
+        .with_header("retry-after", "0")
+        .expect(1)
+        .create();
+    let ok = page_mock(&mut server, "0", PAGE_1);
+    page_mock(&mut server, "40", PAGE_2);
+
+    let mut source = source(&server).with_retry(quick_retries(3));
+    let (reservations, _) = fetch_reservations(&mut source, &query()).unwrap();
+
+    bad_gateway.assert();
+    throttled.assert();
+    ok.assert();
+    assert_eq!(reservations.len(), 3);
+}
+
+#[test]
+fn caps_a_long_retry_after() {
+    let mut server = Server::new();
+    let throttled = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(429)
+        .with_header("retry-after", "86400")
+        .expect(1)
+        .create();
+    page_mock(&mut server, "0", PAGE_1);
+    page_mock(&mut server, "40", PAGE_2);
+
+    let mut source = source(&server).with_retry(quick_retries(1));
+    let started = Instant::now();
+    let (reservations, _) = fetch_reservations(&mut source, &query()).unwrap();
+
+    throttled.assert();
+    assert_eq!(reservations.len(), 3);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn rejects_unusable_rate_limits() {
+    for requests_per_second in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1e-300] {
+        assert!(
+            matches!(RateLimiter::new(requests_per_second), Err(Error::Config(_))),
+            "{}",
+            requests_per_second
+        );
+    }
+    assert!(RateLimiter::new(0.0).is_ok());
+    assert!(RateLimiter::new(2.0).is_ok());
+}
+
+#[test]
+fn gives_up_after_max_retries() {
+    let mut server = Server::new();
+    let mock = server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(503)
+        .expect(3)
+        .create();
+
+    let mut source = source(&server).with_retry(quick_retries(2));
+    let error = fetch_reservations(&mut source, &query()).unwrap_err();
+
+    mock.assert();
+    assert!(matches!(
+        error,
+        Error::HttpStatus { status, .. } if status.as_u16() == 503
+    ));
+}
@@ -0,0 +1,341 @@
+//! Commission rules by listing, owner, zone and default.
+pub mod common;
+
+use airbnb_reservations_scraper::helpers::commission::{
+    CommissionBasis, CommissionDate, CommissionRule, CommissionRules, RuleScope,
+};
+use airbnb_reservations_scraper::helpers::mapping::{row_issues, IssueKind};
+use airbnb_reservations_scraper::helpers::money::Rounding;
+use common::{date, decimal, reservation, stay};
+use polars::prelude::*;
+
+/// S/ 1,250.50 in Depa Miraflores (Lucia, Miraflores), S/ 800.00 in Casa
+/// Barranco (Lucia, Barranco), S/ 400.00 in Loft Surco (Jorge, Miraflores)
+/// and S/ 200.00 in Cuarto Lince, with no owner or zone.
+fn consolidated() -> DataFrame {
+    let (start, end) = ("2024-03-01", "2024-03-04");
+    let mut barranco = stay("HMDEF67890", "Casa Barranco", start, end, "S/ 800.00");
+    barranco.payout_breakdown.cleaning_fee = "S/ 100.00".to_string();
+    common::consolidated(
+        vec![
+            reservation("HMABC12345", "Confirmed"),
+            barranco,
+            stay("HMGHI24680", "Loft Surco", start, end, "S/ 400.00"),
+            stay("HMJKL13579", "Cuarto Lince", start, end, "S/ 200.00"),
+        ],
+        &[Some("Lucia"), Some("Lucia"), Some("Jorge"), None],
+        &[
+            Some("Miraflores"),
+            Some("Barranco"),
+            Some("Miraflores"),
+            None,
+        ],
+        &[],
+    )
+}
+
+fn cents(df: &DataFrame) -> Vec<i64> {
+    df.column("commission_cents")
+        .unwrap()
+        .i64()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+fn rule_ids(df: &DataFrame) -> Vec<&str> {
+    df.column("commission_rule")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+#[test]
+fn applies_the_most_specific_rule() {
+    let rules = CommissionRules::new(vec![
+        CommissionRule::new("base", RuleScope::Default, "", decimal("0.10")),
+        CommissionRule::new("zone", RuleScope::Zone, "Miraflores", decimal("0.12")),
+        CommissionRule::new("lucia", RuleScope::Owner, "Lucia", decimal("0.15")),
+        CommissionRule::new(
+            "depa",
+            RuleScope::Listing,
+            "Depa Miraflores",
+            decimal("0.20"),
+        ),
+    ]);
+
+    let df = rules
+        .apply(
+            consolidated(),
+            "ZONA",
+            CommissionDate::CheckIn,
+            Rounding::HalfUp,
+        )
+        .unwrap();
+
+    assert_eq!(rule_ids(&df), ["depa", "lucia", "zone", "base"]);
+    assert_eq!(cents(&df), [25010, 12000, 4800, 2000]);
+    assert_eq!(
+        df.column("Comision").unwrap().f64().unwrap().get(1),
+        Some(0.15)
+    );
+}
+
+#[test]
+fn applies_tiers_fees_minimums_caps_and_bases() {
+    let tier = |id: &str, rate: &str, min_volume_cents: i64| CommissionRule {
+        min_volume_cents,
+        ..CommissionRule::new(id, RuleScope::Owner, "Lucia", decimal(rate))
+    };
+    let rules = CommissionRules::new(vec![
+        tier("lucia-1", "0.20", 0),
+        // Lucia earns S/ 2,050.50 in March
+        tier("lucia-2", "0.15", 200_000),
+        tier("lucia-3", "0.10", 500_000),
+        CommissionRule {
+            fixed_fee_cents: 2000,
+            cap_cents: Some(5000),
+            ..CommissionRule::new("jorge", RuleScope::Owner, "Jorge", decimal("0.10"))
+        },
+        CommissionRule {
+            minimum_cents: Some(5000),
+            ..CommissionRule::new("base", RuleScope::Default, "", decimal("0.10"))
+        },
+    ]);
+
+    let df = rules
+        .apply(
+            consolidated(),
+            "ZONA",
+            CommissionDate::CheckIn,
+            Rounding::HalfUp,
+        )
+        .unwrap();
+
+    assert_eq!(rule_ids(&df), ["lucia-2", "lucia-2", "jorge", "base"]);
+    // 15% of 1,250.50 and 800.00, 10% of 400.00 plus 20.00 capped, then the minimum
+    assert_eq!(cents(&df), [18758, 12000, 5000, 5000]);
+
+    let net = CommissionRules::new(vec![CommissionRule {
+        basis: CommissionBasis::NetOfCleaning,
+        ..CommissionRule::new("net", RuleScope::Default, "", decimal("0.10"))
+    }]);
+    let df = net
+        .apply(
+            consolidated(),
+            "ZONA",
+            CommissionDate::CheckIn,
+            Rounding::HalfUp,
+        )
+        .unwrap();
+    assert_eq!(cents(&df)[1], 7000);
+
+    let refund = CommissionRule {
+        minimum_cents: Some(5000),
+        ..CommissionRule::new("base", RuleScope::Default, "", decimal("0.10"))
+    };
+    assert_eq!(refund.commission(-12000, Rounding::HalfUp).unwrap(), -1200);
+}
+
+#[test]
+fn reads_a_rules_sheet() {
+    let sheet = df!(
+        "id" => ["depa", "base"],
+        "scope" => ["listing", "default"],
+        "key" => ["Depa Miraflores", ""],
+        "rate" => ["0.2", "0.1"],
+        "basis" => ["net-of-taxes", ""],
+        "minimum" => ["", "25.50"],
+    )
+    .unwrap();
+
+    let rules = CommissionRules::from_dataframe(&sheet).unwrap();
+
+    assert!(rules.covers(RuleScope::Listing, "Depa Miraflores"));
+    assert!(!rules.covers(RuleScope::Listing, "Casa Barranco"));
+    let df = rules
+        .apply(
+            consolidated(),
+            "ZONA",
+            CommissionDate::CheckIn,
+            Rounding::HalfUp,
+        )
+        .unwrap();
+    assert_eq!(rule_ids(&df), ["depa", "base", "base", "base"]);
+    assert_eq!(cents(&df)[3], 2550);
+
+    let bad = df!(
+        "id" => ["depa", "lucia"],
+        "scope" => ["listing", "owner"],
+        "key" => ["Depa Miraflores", ""],
+        "rate" => ["0.2", "0.15"],
+    )
+    .unwrap();
+    let error = CommissionRules::from_dataframe(&bad).unwrap_err();
+    assert!(error.contains("row 3"), "{}", error);
+}
+
+#[test]
+fn keeps_the_rows_without_a_rule_and_reports_them() {
+    let rules = CommissionRules::new(vec![CommissionRule::new(
+        "lucia",
+        RuleScope::Owner,
+        "Lucia",
+        decimal("0.15"),
+    )]);
+
+    let df = rules
+        .apply(
+            consolidated(),
+            "ZONA",
+            CommissionDate::CheckIn,
+            Rounding::HalfUp,
+        )
+        .unwrap();
+
+    let cents: Vec<Option<i64>> = df
+        .column("commission_cents")
+        .unwrap()
+        .i64()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(cents, [Some(18758), Some(12000), None, None]);
+    let rule_ids: Vec<Option<&str>> = df
+        .column("commission_rule")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(rule_ids, [Some("lucia"), Some("lucia"), None, None]);
+    let errors = df.column("commission_error").unwrap().str().unwrap();
+    assert_eq!(errors.get(0), None);
+    assert!(
+        errors
+            .get(2)
+            .unwrap()
+            .contains("no commission rule applies"),
+        "{:?}",
+        errors.get(2)
+    );
+
+    let issues: Vec<(IssueKind, String)> = row_issues(&df)
+        .unwrap()
+        .into_iter()
+        .map(|issue| (issue.kind, issue.key))
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            (IssueKind::NoCommission, "HMGHI24680".to_string()),
+            (IssueKind::NoCommission, "HMJKL13579".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn applies_the_agreement_in_force_on_the_reference_date() {
+    let agreement = |id: &str, rate: &str, from: &str, to: Option<&str>| CommissionRule {
+        valid_from: date(from),
+        valid_to: to.and_then(date),
+        ..CommissionRule::new(id, RuleScope::Owner, "Lucia", decimal(rate))
+    };
+    let rules = CommissionRules::new(vec![
+        agreement("lucia-2023", "0.20", "2023-01-01", Some("2024-02-29")),
+        agreement("lucia-2024", "0.15", "2024-03-01", None),
+        CommissionRule::new("base", RuleScope::Default, "", decimal("0.10")),
+    ]);
+    let agreements = |df: &DataFrame| -> Vec<String> {
+        df.column("commission_agreement")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .map(|id| id.unwrap().to_string())
+            .collect()
+    };
+
+    // Booked on 2024-02-10, checked in on 2024-03-01
+    let booked = rules
+        .apply(
+            consolidated(),
+            "ZONA",
+            CommissionDate::Booked,
+            Rounding::HalfUp,
+        )
+        .unwrap();
+    let check_in = rules
+        .apply(
+            consolidated(),
+            "ZONA",
+            CommissionDate::CheckIn,
+            Rounding::HalfUp,
+        )
+        .unwrap();
+
+    assert_eq!(agreements(&booked)[..2], ["lucia-2023", "lucia-2023"]);
+    assert_eq!(cents(&booked)[..2], [25010, 16000]);
+    assert_eq!(agreements(&check_in)[..2], ["lucia-2024", "lucia-2024"]);
+    assert_eq!(agreements(&check_in)[3], "base");
+}
+
+#[test]
+fn reads_dated_owner_rates_and_rejects_overlaps() {
+    let owners = df!(
+        "PROPIETARIO" => ["Lucia", "Lucia", "Jorge", "Ana"],
+        "Comision" => ["0.20", "0.15", "0.12", ""],
+        "valid_from" => ["2023-01-01", "45352", "", ""],
+        "valid_to" => ["2024-02-29", "", "", ""],
+    )
+    .unwrap();
+    let mut rules = CommissionRules::new(vec![CommissionRule::new(
+        "base",
+        RuleScope::Default,
+        "",
+        decimal("0.10"),
+    )]);
+
+    rules.push_owner_rates(&owners).unwrap();
+
+    assert!(rules.covers(RuleScope::Owner, "Jorge"));
+    assert!(!rules.covers(RuleScope::Owner, "Ana"));
+    let df = rules
+        .apply(
+            consolidated(),
+            "ZONA",
+            CommissionDate::CheckIn,
+            Rounding::HalfUp,
+        )
+        .unwrap();
+    // 45352 is 2024-03-01 as an Excel date
+    assert_eq!(
+        rule_ids(&df),
+        [
+            "owner:Lucia:2024-03-01",
+            "owner:Lucia:2024-03-01",
+            "owner:Jorge",
+            "base"
+        ]
+    );
+
+    let overlapping = df!(
+        "id" => ["lucia-2023", "lucia-2024"],
+        "scope" => ["owner", "owner"],
+        "key" => ["Lucia", "Lucia"],
+        "rate" => ["0.20", "0.15"],
+        "valid_from" => ["2023-01-01", "2024-03-01"],
+        "valid_to" => ["2024-03-01", ""],
+    )
+    .unwrap();
+    let error = CommissionRules::from_dataframe(&overlapping).unwrap_err();
+    assert!(
+        error.contains("lucia-2023 and lucia-2024 overlap"),
+        "{}",
+        error
+    );
+}
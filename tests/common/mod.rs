@@ -0,0 +1,76 @@
+//! Helpers shared by the integration tests. Each test crate declares it
+//! `pub mod common;`, so the helpers it does not call are not reported as
+//! unused.
+use airbnb_reservations_scraper::helpers::engine::{get_dataframe, process_data};
+use airbnb_reservations_scraper::Reservation;
+use chrono::NaiveDate;
+use polars::prelude::*;
+use rust_decimal::Decimal;
+
+pub fn date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+pub fn reservation(code: &str, status: &str) -> Reservation {
+    Reservation {
+        confirmation_code: code.to_string(),
+        status: status.to_string(),
+        guest_user_full_name: "Ana Torres".to_string(),
+        guest_user_phone: "+51 999 111 222".to_string(),
+        guest_details_number_of_adults: 2.0,
+        guest_details_number_of_children: 0.0,
+        guest_details_number_of_infants: 0.0,
+        start_date: date("2024-03-01"),
+        end_date: date("2024-03-04"),
+        nights: 3.0,
+        booked_date: date("2024-02-10"),
+        listing_name: "Depa Miraflores".to_string(),
+        earnings: "S/ 1,250.50".to_string(),
+        ..Default::default()
+    }
+}
+
+/// A confirmed stay in `listing` from `start` to `end`, earning `earnings`.
+pub fn stay(code: &str, listing: &str, start: &str, end: &str, earnings: &str) -> Reservation {
+    let (start_date, end_date) = (date(start), date(end));
+    Reservation {
+        listing_name: listing.to_string(),
+        start_date,
+        end_date,
+        nights: (end_date.unwrap() - start_date.unwrap()).num_days() as f64,
+        earnings: earnings.to_string(),
+        ..reservation(code, "Confirmed")
+    }
+}
+
+/// `reservations` processed in PEN, with the `PROPIETARIO` and `ZONA` the
+/// base workbook maps each one to and their `commission_cents`; an empty
+/// slice leaves the column out.
+pub fn consolidated(
+    reservations: Vec<Reservation>,
+    owners: &[Option<&str>],
+    zones: &[Option<&str>],
+    commission_cents: &[i64],
+) -> DataFrame {
+    let df = process_data(get_dataframe(reservations).unwrap(), "PEN").unwrap();
+    let mut columns = Vec::new();
+    if !commission_cents.is_empty() {
+        columns.push(Series::new("commission_cents", commission_cents));
+    }
+    if !owners.is_empty() {
+        columns.push(Series::new("PROPIETARIO", owners));
+    }
+    if !zones.is_empty() {
+        columns.push(Series::new("ZONA", zones));
+    }
+    df.hstack(&columns).unwrap()
+}
+
+pub fn floats(df: &DataFrame, name: &str) -> Vec<Option<f64>> {
+    let column = df.column(name).unwrap().cast(&DataType::Float64).unwrap();
+    column.f64().unwrap().into_iter().collect()
+}
+
+pub fn decimal(value: &str) -> Decimal {
+    value.parse().unwrap()
+}
@@ -0,0 +1,88 @@
+//! Typed columns of the reservations frame.
+pub mod common;
+
+use airbnb_reservations_scraper::helpers::engine::{get_dataframe, restore_dates, select_columns};
+use common::{date, reservation};
+use polars::prelude::*;
+use std::io::Cursor;
+
+#[test]
+fn stores_dates_as_date_columns() {
+    let mut unparsed = reservation("HMDEF67890", "Confirmed");
+    unparsed.booked_date = None;
+    let df = get_dataframe(vec![reservation("HMABC12345", "Confirmed"), unparsed]).unwrap();
+
+    for name in ["start_date", "end_date", "booked_date"] {
+        assert_eq!(
+            df.column(name).unwrap().dtype(),
+            &DataType::Date,
+            "{}",
+            name
+        );
+    }
+    let booked = df.column("booked_date").unwrap();
+    assert_eq!(booked.null_count(), 1);
+    assert_eq!(
+        booked.get(0).unwrap(),
+        AnyValue::Date(
+            (date("2024-02-10").unwrap() - date("1970-01-01").unwrap()).num_days() as i32
+        )
+    );
+}
+
+#[test]
+fn restores_dates_read_back_from_json() {
+    let mut df = get_dataframe(vec![reservation("HMABC12345", "Confirmed")]).unwrap();
+    let mut json = Vec::new();
+    JsonWriter::new(&mut json)
+        .with_json_format(JsonFormat::Json)
+        .finish(&mut df)
+        .unwrap();
+
+    let read = JsonReader::new(Cursor::new(json)).finish().unwrap();
+    let restored = restore_dates(read).unwrap();
+
+    assert_eq!(
+        restored.column("start_date").unwrap(),
+        df.column("start_date").unwrap()
+    );
+}
+
+#[test]
+fn selects_export_columns_and_keeps_added_ones() {
+    let mut with_payout = reservation("HMABC12345", "Confirmed");
+    with_payout.payout_breakdown.cleaning_fee = "S/ 150.00".to_string();
+    let df = get_dataframe(vec![with_payout]).unwrap();
+    let df = df
+        .hstack(&[
+            Series::new("amount", [1250.5]),
+            Series::new("Comision", [0.2]),
+        ])
+        .unwrap();
+
+    let columns = [
+        "payout_breakdown_cleaning_fee",
+        "confirmation_code",
+        "no_such_column",
+    ]
+    .map(String::from);
+    let selected = select_columns(df, &columns).unwrap();
+
+    assert_eq!(
+        selected.get_column_names(),
+        [
+            "payout_breakdown_cleaning_fee",
+            "confirmation_code",
+            "amount",
+            "Comision"
+        ]
+    );
+    assert_eq!(
+        selected
+            .column("payout_breakdown_cleaning_fee")
+            .unwrap()
+            .get(0)
+            .unwrap(),
+        AnyValue::String("S/ 150.00")
+    );
+}
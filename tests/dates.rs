@@ -0,0 +1,75 @@
+//! Parsing and normalization of user supplied dates.
+use airbnb_reservations_scraper::helpers::dates::{
+    normalize_date, sheet_date, validate_range, DateBound,
+};
+use chrono::NaiveDate;
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, 15).unwrap()
+}
+
+fn start(input: &str) -> String {
+    normalize_date(input, DateBound::Start, today()).unwrap()
+}
+
+fn end(input: &str) -> String {
+    normalize_date(input, DateBound::End, today()).unwrap()
+}
+
+#[test]
+fn accepts_absolute_formats() {
+    assert_eq!(start("2024-01-05"), "2024-01-05");
+    assert_eq!(start("05/01/2024"), "2024-01-05");
+    assert_eq!(start("05-01-2024"), "2024-01-05");
+    assert_eq!(start(" 2024-01-05\n"), "2024-01-05");
+}
+
+#[test]
+fn resolves_relative_offsets() {
+    assert_eq!(start("today"), "2024-03-15");
+    assert_eq!(start("yesterday"), "2024-03-14");
+    assert_eq!(start("-30d"), "2024-02-14");
+    assert_eq!(start("-2w"), "2024-03-01");
+    assert_eq!(start("-1m"), "2024-02-15");
+}
+
+#[test]
+fn resolves_periods_to_their_first_or_last_day() {
+    assert_eq!(start("this-month"), "2024-03-01");
+    assert_eq!(end("this-month"), "2024-03-31");
+    assert_eq!(start("last-month"), "2024-02-01");
+    assert_eq!(end("last-month"), "2024-02-29");
+    assert_eq!(start("this-year"), "2024-01-01");
+    assert_eq!(end("last-year"), "2023-12-31");
+}
+
+#[test]
+fn rejects_bad_input_with_the_accepted_formats() {
+    for input in ["", "2024-13-01", "31/02/2024", "next-month", "-5x", "-d"] {
+        let error = normalize_date(input, DateBound::Start, today()).unwrap_err();
+        assert!(
+            error.to_string().contains("YYYY-MM-DD"),
+            "unexpected message for {:?}: {}",
+            input,
+            error
+        );
+    }
+}
+
+#[test]
+fn rejects_empty_windows() {
+    assert!(validate_range("2024-01-01", Some("2024-01-31")).is_ok());
+    assert!(validate_range("2024-01-01", None).is_ok());
+    assert!(validate_range("2024-02-01", Some("2024-01-31")).is_err());
+    assert!(validate_range("2024-01-01\n", None).is_err());
+}
+
+#[test]
+fn reads_sheet_dates_and_rejects_serials_out_of_range() {
+    assert_eq!(sheet_date("2024-03-15"), Some(today()));
+    assert_eq!(sheet_date("45366"), Some(today()));
+    assert_eq!(sheet_date("45366.75"), Some(today()));
+    for serial in ["1e9", "1e15", "1e300", "-1e300", "NaN", "inf", "abc"] {
+        assert_eq!(sheet_date(serial), None, "{}", serial);
+    }
+}
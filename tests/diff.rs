@@ -0,0 +1,78 @@
+//! Change detection between two reservation snapshots.
+pub mod common;
+
+use airbnb_reservations_scraper::helpers::diff::{diff_reservations, ChangeKind};
+use common::{date, reservation};
+use serde_json::json;
+
+#[test]
+fn classifies_new_cancelled_modified_and_disappeared() {
+    let mut moved = reservation("HMDEF67890", "Confirmed");
+    let previous = vec![
+        reservation("HMABC12345", "Confirmed"),
+        moved.clone(),
+        reservation("HMGHI24680", "Confirmed"),
+        reservation("HMJKL13579", "Confirmed"),
+    ];
+    moved.end_date = date("2024-03-06");
+    moved.nights = 5.0;
+    let current = vec![
+        reservation("HMABC12345", "Cancelled by guest"),
+        moved,
+        reservation("HMGHI24680", "Confirmed"),
+        reservation("HMMNO97531", "Confirmed"),
+    ];
+
+    let diff = diff_reservations(&previous, &current);
+
+    let kinds: Vec<(&str, ChangeKind)> = diff
+        .reservations
+        .iter()
+        .map(|change| (change.confirmation_code.as_str(), change.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            ("HMABC12345", ChangeKind::Cancelled),
+            ("HMDEF67890", ChangeKind::Modified),
+            ("HMJKL13579", ChangeKind::Disappeared),
+            ("HMMNO97531", ChangeKind::New),
+        ]
+    );
+    assert_eq!(
+        (diff.new, diff.cancelled, diff.modified, diff.disappeared),
+        (1, 1, 1, 1)
+    );
+}
+
+#[test]
+fn reports_field_level_before_and_after() {
+    let previous = vec![reservation("HMDEF67890", "Confirmed")];
+    let mut changed = reservation("HMDEF67890", "Confirmed");
+    changed.end_date = date("2024-03-06");
+    changed.nights = 5.0;
+
+    let diff = diff_reservations(&previous, &[changed]);
+
+    let changes: Vec<_> = diff.reservations[0]
+        .changes
+        .iter()
+        .map(|change| (change.field.as_str(), &change.before, &change.after))
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            ("end_date", &json!("2024-03-04"), &json!("2024-03-06")),
+            ("nights", &json!(3.0), &json!(5.0)),
+        ]
+    );
+}
+
+#[test]
+fn identical_snapshots_have_no_changes() {
+    let snapshot = vec![
+        reservation("HMABC12345", "Confirmed"),
+        reservation("HMDEF67890", "Confirmed"),
+    ];
+    assert!(diff_reservations(&snapshot, &snapshot).is_empty());
+}
@@ -0,0 +1,81 @@
+//! Exit codes of each failure family, relied on by wrapper scripts.
+use airbnb_reservations_scraper::Error;
+use polars::prelude::PolarsError;
+use reqwest::StatusCode;
+use std::ffi::CString;
+use std::io;
+use xlsxwriter::XlsxError;
+
+fn http_error() -> reqwest::Error {
+    reqwest::blocking::get("not a url").unwrap_err()
+}
+
+fn xlsx_error() -> XlsxError {
+    CString::new("nul\0byte").unwrap_err().into()
+}
+
+#[test]
+fn maps_every_error_to_its_exit_code() {
+    let url = "https://www.airbnb.com/api/v2/reservations".to_string();
+    let cases = [
+        (Error::Config("MAX_RETRIES".into()), 2),
+        (Error::ServiceDisabled, 3),
+        (
+            Error::Secrets {
+                key: "_AAT".into(),
+                message: "not set".into(),
+            },
+            4,
+        ),
+        (
+            Error::Auth {
+                credential: "_AAT".into(),
+                refreshed: true,
+            },
+            5,
+        ),
+        (
+            Error::Http {
+                url: url.clone(),
+                source: http_error(),
+            },
+            6,
+        ),
+        (
+            Error::HttpStatus {
+                url,
+                status: StatusCode::BAD_GATEWAY,
+            },
+            6,
+        ),
+        (Error::Schema("reservations is not an array".into()), 7),
+        (Error::parse("earnings", "not an amount"), 7),
+        (
+            Error::ExcelRead {
+                path: "base.xlsx".into(),
+                message: "not found".into(),
+            },
+            8,
+        ),
+        (
+            Error::mapping_sheet("Anuncios", "missing column ANUNCIO"),
+            8,
+        ),
+        (
+            Error::ExcelWrite {
+                path: "out.xlsx".into(),
+                source: xlsx_error(),
+            },
+            9,
+        ),
+        (
+            Error::io("Could not write the diff", io::ErrorKind::Other.into()),
+            9,
+        ),
+        (Error::Polars(PolarsError::ComputeError("failed".into())), 9),
+    ];
+
+    for (error, code) in cases {
+        assert_eq!(error.exit_code(), code, "{}", error);
+    }
+}
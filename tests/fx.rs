@@ -0,0 +1,135 @@
+//! Conversion into the reporting currency with a local rate table.
+pub mod common;
+
+use airbnb_reservations_scraper::helpers::engine::{get_dataframe, process_data};
+use airbnb_reservations_scraper::helpers::fx::{convert, RateProvider, RateTable};
+use airbnb_reservations_scraper::helpers::mapping::{row_issues, IssueKind};
+use airbnb_reservations_scraper::helpers::money::Rounding;
+use common::{date, decimal, reservation};
+use rust_decimal::Decimal;
+use std::env;
+use std::fs;
+
+fn rates(name: &str) -> RateTable {
+    let path = env::temp_dir().join(format!("fx-rates-{}-{}.csv", name, std::process::id()));
+    fs::write(
+        &path,
+        "date,from,to,rate\n2024-01-01,USD,PEN,3.70\n2024-03-01,USD,PEN,3.75\n2024-01-01,eur,pen,4.10\n",
+    )
+    .unwrap();
+    let table = RateTable::open(&path, "rates").unwrap();
+    fs::remove_file(&path).unwrap();
+    table
+}
+
+#[test]
+fn uses_the_last_rate_on_or_before_the_date() {
+    let table = rates("lookup");
+    let rate = |from, to, day| table.rate(from, to, date(day).unwrap());
+
+    assert_eq!(rate("USD", "PEN", "2024-02-29").unwrap(), decimal("3.70"));
+    assert_eq!(rate("USD", "PEN", "2024-03-01").unwrap(), decimal("3.75"));
+    assert_eq!(rate("EUR", "PEN", "2024-06-01").unwrap(), decimal("4.10"));
+    assert_eq!(
+        rate("PEN", "USD", "2024-03-02").unwrap(),
+        Decimal::ONE / decimal("3.75")
+    );
+    assert_eq!(rate("PEN", "PEN", "2020-01-01").unwrap(), Decimal::ONE);
+    assert!(rate("USD", "PEN", "2023-12-31").is_err());
+    assert!(rate("GBP", "PEN", "2024-03-01").is_err());
+}
+
+#[test]
+fn reads_csv_rates_with_every_digit() {
+    let path = env::temp_dir().join(format!("fx-rates-precise-{}.csv", std::process::id()));
+    fs::write(
+        &path,
+        "date,from,to,rate\n2024-01-01,USD,PEN,3.7512345678\n2024-01-01,CLP,USD,0.000000271234\n2024-01-01,VES,USD,2.5e-8\n",
+    )
+    .unwrap();
+    let table = RateTable::open(&path, "rates").unwrap();
+    fs::remove_file(&path).unwrap();
+    let rate = |from, to| table.rate(from, to, date("2024-03-01").unwrap()).unwrap();
+
+    assert_eq!(rate("USD", "PEN"), decimal("3.7512345678"));
+    assert_eq!(rate("CLP", "USD"), decimal("0.000000271234"));
+    assert_eq!(rate("VES", "USD"), decimal("0.000000025"));
+}
+
+#[test]
+fn adds_converted_amounts_next_to_the_originals() {
+    let mut dollars = reservation("HMDEF67890", "Confirmed");
+    dollars.earnings = "US$100.10".to_string();
+    let df = get_dataframe(vec![reservation("HMABC12345", "Confirmed"), dollars]).unwrap();
+    let df = process_data(df, "PEN").unwrap();
+
+    let converted = convert(df, &rates("convert"), "PEN", Rounding::HalfUp).unwrap();
+
+    let column = |name| converted.column(name).unwrap().clone();
+    assert_eq!(column("amount_cents").i64().unwrap().get(1), Some(10010));
+    assert_eq!(column("currency").str().unwrap().get(1), Some("USD"));
+    // 100.10 USD at 3.75 (the rate of 2024-03-01) is 375.375 PEN
+    assert_eq!(
+        column("converted_amount_cents").i64().unwrap().get(1),
+        Some(37538)
+    );
+    assert_eq!(
+        column("converted_amount_cents").i64().unwrap().get(0),
+        Some(125050)
+    );
+    assert_eq!(column("fx_rate").f64().unwrap().get(1), Some(3.75));
+    assert_eq!(
+        column("reporting_currency").str().unwrap().get(0),
+        Some("PEN")
+    );
+}
+
+#[test]
+fn keeps_the_rows_without_a_rate_and_reports_them() {
+    let mut pounds = reservation("HMDEF67890", "Confirmed");
+    pounds.earnings = "£80.00".to_string();
+    let mut pending = reservation("HMGHI24680", "Confirmed");
+    pending.earnings = "pending".to_string();
+    let df = get_dataframe(vec![
+        reservation("HMABC12345", "Confirmed"),
+        pounds,
+        pending,
+    ])
+    .unwrap();
+    let df = process_data(df, "PEN").unwrap();
+
+    let converted = convert(df, &rates("missing"), "PEN", Rounding::HalfUp).unwrap();
+
+    let column = |name| converted.column(name).unwrap().clone();
+    let cents: Vec<Option<i64>> = column("converted_amount_cents")
+        .i64()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(cents, [Some(125050), None, None]);
+    let rates: Vec<Option<f64>> = column("fx_rate").f64().unwrap().into_iter().collect();
+    assert_eq!(rates, [Some(1.0), None, None]);
+    let errors = column("fx_error");
+    let errors = errors.str().unwrap();
+    assert_eq!(errors.get(0), None);
+    assert!(
+        errors.get(1).unwrap().contains("no GBP to PEN rate"),
+        "{:?}",
+        errors.get(1)
+    );
+    // Reported as invalid earnings instead
+    assert_eq!(errors.get(2), None);
+
+    let issues: Vec<(IssueKind, String)> = row_issues(&converted)
+        .unwrap()
+        .into_iter()
+        .map(|issue| (issue.kind, issue.key))
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            (IssueKind::InvalidEarnings, "HMGHI24680".to_string()),
+            (IssueKind::NoExchangeRate, "HMDEF67890".to_string()),
+        ]
+    );
+}
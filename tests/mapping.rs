@@ -0,0 +1,82 @@
+//! Issues of the listing and owner sheets of the base workbook.
+pub mod common;
+
+use airbnb_reservations_scraper::helpers::engine::get_dataframe;
+use airbnb_reservations_scraper::helpers::mapping::{validate, IssueKind};
+use airbnb_reservations_scraper::helpers::registry::ListingRegistry;
+use common::reservation;
+use polars::prelude::*;
+
+#[test]
+fn reports_unmapped_duplicate_unused_and_unknown_keys() {
+    let mut renamed = reservation("HMDEF67890", "Confirmed");
+    renamed.listing_name = "Depa Miraflores (renovado)".to_string();
+    let mut barranco = reservation("HMGHI24680", "Confirmed");
+    barranco.listing_name = "Casa Barranco".to_string();
+    let reservations = get_dataframe(vec![
+        reservation("HMABC12345", "Confirmed"),
+        renamed,
+        barranco,
+    ])
+    .unwrap();
+    let listings = df!(
+        "ANUNCIO" => ["Depa Miraflores", "Casa Barranco", "Casa Barranco", "Loft Surco", ""],
+        "PROPIETARIO" => ["Lucia", "Lucia", "Lucia", "Jorge", ""],
+    )
+    .unwrap();
+    let owners = df!(
+        "PROPIETARIO" => ["Lucia"],
+        "Comision" => ["0.15"],
+    )
+    .unwrap();
+
+    let registry = ListingRegistry::from_dataframe(&listings, 0.8).unwrap();
+
+    let issues = validate(&reservations, &registry, &listings, &owners).unwrap();
+
+    let found: Vec<(IssueKind, &str)> = issues
+        .iter()
+        .map(|issue| (issue.kind, issue.key.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (IssueKind::UnmappedListing, "Depa Miraflores (renovado)"),
+            (IssueKind::DuplicateListing, "Casa Barranco"),
+            (IssueKind::UnknownOwner, "Jorge"),
+            (IssueKind::UnusedListing, "Loft Surco"),
+        ]
+    );
+    assert!(
+        issues[0].detail.contains("HMDEF67890"),
+        "{}",
+        issues[0].detail
+    );
+    assert!(
+        issues[1].detail.contains("lines 3, 4"),
+        "{}",
+        issues[1].detail
+    );
+    assert!(
+        issues[2].detail.contains("Loft Surco"),
+        "{}",
+        issues[2].detail
+    );
+}
+
+#[test]
+fn finds_nothing_in_a_consistent_workbook() {
+    let reservations = get_dataframe(vec![reservation("HMABC12345", "Confirmed")]).unwrap();
+    let listings = df!(
+        "ANUNCIO" => ["Depa Miraflores"],
+        "PROPIETARIO" => ["Lucia"],
+    )
+    .unwrap();
+    let owners = df!("PROPIETARIO" => ["Lucia", "Lucia"]).unwrap();
+
+    let registry = ListingRegistry::from_dataframe(&listings, 0.8).unwrap();
+
+    assert!(validate(&reservations, &registry, &listings, &owners)
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,235 @@
+//! Earnings formats of the locales Airbnb emits.
+pub mod common;
+
+use airbnb_reservations_scraper::helpers::engine::{get_dataframe, process_data};
+use airbnb_reservations_scraper::helpers::mapping::{row_issues, IssueKind};
+use airbnb_reservations_scraper::helpers::money::{
+    apply_rate, from_cents, parse_money, to_cents, Money, Rounding,
+};
+use common::{decimal, reservation};
+use polars::prelude::*;
+use rust_decimal::Decimal;
+
+fn money(currency: &str, amount: f64) -> Money {
+    Money {
+        currency: currency.to_string(),
+        amount: amount.to_string().parse::<Decimal>().unwrap(),
+    }
+}
+
+#[test]
+fn parses_prefix_symbols() {
+    assert_eq!(
+        parse_money("S/ 1,250.50", "PEN").unwrap(),
+        money("PEN", 1250.5)
+    );
+    assert_eq!(
+        parse_money("S/.480.00", "PEN").unwrap(),
+        money("PEN", 480.0)
+    );
+    assert_eq!(
+        parse_money("US$1,200.00", "PEN").unwrap(),
+        money("USD", 1200.0)
+    );
+    assert_eq!(
+        parse_money("R$ 1.250,50", "BRL").unwrap(),
+        money("BRL", 1250.5)
+    );
+    assert_eq!(parse_money("£99.99", "USD").unwrap(), money("GBP", 99.99));
+}
+
+#[test]
+fn parses_suffix_symbols_and_codes() {
+    assert_eq!(
+        parse_money("1.250,50 €", "EUR").unwrap(),
+        money("EUR", 1250.5)
+    );
+    assert_eq!(
+        parse_money("1 250,50\u{a0}€", "EUR").unwrap(),
+        money("EUR", 1250.5)
+    );
+    assert_eq!(
+        parse_money("120,00 zł", "PLN").unwrap(),
+        money("PLN", 120.0)
+    );
+    assert_eq!(
+        parse_money("PEN 300.00", "USD").unwrap(),
+        money("PEN", 300.0)
+    );
+    assert_eq!(
+        parse_money("CHF 1'250.50", "CHF").unwrap(),
+        money("CHF", 1250.5)
+    );
+}
+
+#[test]
+fn parses_amounts_without_decimals() {
+    assert_eq!(
+        parse_money("S/ 1,250", "PEN").unwrap(),
+        money("PEN", 1250.0)
+    );
+    assert_eq!(
+        parse_money("¥12,000", "JPY").unwrap(),
+        money("JPY", 12000.0)
+    );
+    assert_eq!(parse_money("$85", "USD").unwrap(), money("USD", 85.0));
+    assert_eq!(parse_money("1,5 €", "EUR").unwrap(), money("EUR", 1.5));
+}
+
+#[test]
+fn parses_refunds() {
+    assert_eq!(
+        parse_money("-S/ 120.00", "PEN").unwrap(),
+        money("PEN", -120.0)
+    );
+    assert_eq!(
+        parse_money("S/ -120.00", "PEN").unwrap(),
+        money("PEN", -120.0)
+    );
+    assert_eq!(parse_money("−€50,00", "EUR").unwrap(), money("EUR", -50.0));
+    assert_eq!(
+        parse_money("(US$75.25)", "USD").unwrap(),
+        money("USD", -75.25)
+    );
+}
+
+#[test]
+fn resolves_bare_amounts_and_dollars_with_the_default_currency() {
+    assert_eq!(
+        parse_money("1,250.50", "PEN").unwrap(),
+        money("PEN", 1250.5)
+    );
+    assert_eq!(
+        parse_money("$1,250.50", "MXN").unwrap(),
+        money("MXN", 1250.5)
+    );
+    assert_eq!(
+        parse_money("$1,250.50", "PEN").unwrap(),
+        money("USD", 1250.5)
+    );
+}
+
+#[test]
+fn rejects_malformed_amounts() {
+    assert!(parse_money("", "PEN").is_err());
+    assert!(parse_money("S/ --", "PEN").is_err());
+    assert!(parse_money("1.250.50,00.1", "PEN").is_err());
+    assert!(parse_money("XYZW 10.00", "PEN").is_err());
+    assert!(parse_money("(S/ 10.00", "PEN").is_err());
+}
+
+#[test]
+fn keeps_the_rows_that_fail_and_reports_them() {
+    let mut no_amount = reservation("HMDEF67890", "Confirmed");
+    no_amount.earnings = "pending".to_string();
+    let mut unknown = reservation("HMGHI24680", "Confirmed");
+    unknown.earnings = "?? 10.00".to_string();
+    let df = get_dataframe(vec![
+        reservation("HMABC12345", "Confirmed"),
+        no_amount,
+        unknown,
+    ])
+    .unwrap();
+
+    let df = process_data(df, "PEN").unwrap();
+
+    assert_eq!(df.height(), 3);
+    let currencies: Vec<Option<&str>> = df
+        .column("currency")
+        .unwrap()
+        .str()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(currencies, [Some("PEN"), None, None]);
+    let cents: Vec<Option<i64>> = df
+        .column("amount_cents")
+        .unwrap()
+        .i64()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(cents, [Some(125050), None, None]);
+    let amounts: Vec<Option<f64>> = df
+        .column("amount")
+        .unwrap()
+        .f64()
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(amounts, [Some(1250.5), None, None]);
+    let errors = df.column("earnings_error").unwrap().str().unwrap();
+    assert_eq!(errors.get(0), None);
+    assert!(
+        errors.get(1).unwrap().contains("pending"),
+        "{:?}",
+        errors.get(1)
+    );
+    assert!(errors.get(2).is_some());
+
+    let issues = row_issues(&df).unwrap();
+    let keys: Vec<(IssueKind, &str)> = issues
+        .iter()
+        .map(|issue| (issue.kind, issue.key.as_str()))
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            (IssueKind::InvalidEarnings, "HMDEF67890"),
+            (IssueKind::InvalidEarnings, "HMGHI24680"),
+        ]
+    );
+}
+
+#[test]
+fn adds_no_error_column_when_every_row_parses() {
+    let df = get_dataframe(vec![reservation("HMABC12345", "Confirmed")]).unwrap();
+
+    let df = process_data(df, "PEN").unwrap();
+
+    assert!(df.column("earnings_error").is_err());
+    assert!(row_issues(&df).unwrap().is_empty());
+}
+
+#[test]
+fn rounds_commissions_with_each_mode() {
+    // 15% of S/ 10.10 and of S/ 10.30 is 151.5 and 154.5 cents
+    let cases = [
+        (Rounding::HalfUp, 152, 155, -152),
+        (Rounding::HalfDown, 151, 154, -151),
+        (Rounding::HalfEven, 152, 154, -152),
+        (Rounding::Up, 152, 155, -152),
+        (Rounding::Down, 151, 154, -151),
+    ];
+    for (rounding, first, second, refund) in cases {
+        let rate = decimal("0.15");
+        assert_eq!(apply_rate(1010, rate, rounding).unwrap(), first);
+        assert_eq!(apply_rate(1030, rate, rounding).unwrap(), second);
+        assert_eq!(apply_rate(-1010, rate, rounding).unwrap(), refund);
+    }
+    assert_eq!("half-even".parse::<Rounding>().unwrap(), Rounding::HalfEven);
+    assert!("nearest".parse::<Rounding>().is_err());
+}
+
+#[test]
+fn keeps_amounts_exact_in_cents() {
+    assert_eq!(
+        to_cents(decimal("1250.50"), Rounding::HalfUp).unwrap(),
+        125050
+    );
+    assert_eq!(from_cents(125050), decimal("1250.50"));
+
+    let mut second = reservation("HMDEF67890", "Confirmed");
+    second.earnings = "S/ 0.20".to_string();
+    let mut third = reservation("HMGHI24680", "Confirmed");
+    third.earnings = "S/ 0.10".to_string();
+    let df = get_dataframe(vec![reservation("HMABC12345", "Confirmed"), second, third]).unwrap();
+    let df = process_data(df, "PEN").unwrap();
+
+    let cents = df.column("amount_cents").unwrap().i64().unwrap();
+    assert_eq!(cents.sum(), Some(125080));
+    assert_eq!(
+        df.column("currency").unwrap().get(0).unwrap(),
+        AnyValue::String("PEN")
+    );
+}
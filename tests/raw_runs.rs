@@ -0,0 +1,115 @@
+//! Raw pages saved while fetching, and fetches replayed from them.
+use airbnb_reservations_scraper::helpers::engine::fetch_reservations;
+use airbnb_reservations_scraper::models::query::{ReservationQuery, ReservationStatus};
+use airbnb_reservations_scraper::sources::airbnb::AirbnbSource;
+use airbnb_reservations_scraper::sources::recording::{RecordingSource, RunManifest};
+use airbnb_reservations_scraper::sources::replay::ReplaySource;
+use airbnb_reservations_scraper::sources::retry::RetryPolicy;
+use airbnb_reservations_scraper::sources::{Attempt, ReservationSource};
+use mockito::{Matcher, Server};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+fn run_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("raw-run-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn fixtures() -> Box<dyn ReservationSource> {
+    Box::new(ReplaySource::new(Path::new("tests/fixtures/reservations")).unwrap())
+}
+
+#[test]
+fn records_pages_and_replays_them() {
+    let dir = run_dir("replay");
+    let mut query = ReservationQuery::new("2024-01-01");
+    query.statuses = vec![ReservationStatus::Accepted];
+    let mut recording = RecordingSource::new(fixtures(), &dir).unwrap();
+    let (fetched, _) = fetch_reservations(&mut recording, &query).unwrap();
+
+    let manifest = RunManifest::read(&dir).unwrap();
+    assert_eq!(manifest.pages.len(), 2);
+    assert_eq!(manifest.pages[1].offset, 40);
+    // Replayed pages never reached the API
+    assert_eq!(manifest.pages[1].status, None);
+    assert!(manifest.pages[1].attempts.is_empty());
+    assert_eq!(manifest.pages[1].file.as_deref(), Some("page_0002.json.gz"));
+    assert_eq!(
+        manifest.query.get("status").map(String::as_str),
+        Some("accepted")
+    );
+    assert!(!manifest.query.contains_key("_offset"));
+
+    let recorded = ReservationQuery::from_params(&manifest.query.into_iter().collect()).unwrap();
+    assert_eq!(recorded, query);
+    let (replayed, _) =
+        fetch_reservations(&mut ReplaySource::new(&dir).unwrap(), &recorded).unwrap();
+    assert_eq!(replayed, fetched);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn records_failed_pages() {
+    let dir = run_dir("failed");
+    let mut query = ReservationQuery::new("2024-01-01");
+    query.page_size = 1000;
+    let mut recording = RecordingSource::new(fixtures(), &dir).unwrap();
+    let params = query.to_params(5000);
+
+    assert!(recording.fetch_page(&params).is_err());
+
+    let page = &RunManifest::read(&dir).unwrap().pages[0];
+    assert_eq!(page.offset, 5000);
+    assert_eq!(page.status, None);
+    assert_eq!(page.file, None);
+    assert!(page.error.as_deref().unwrap().contains("No saved page"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn records_the_status_of_every_attempt() {
+    let dir = run_dir("attempts");
+    let mut server = Server::new();
+    server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(429)
+        .with_header("retry-after", "0")
+        .expect(1)
+        .create();
+    server
+        .mock("GET", "/api/v2/reservations")
+        .match_query(Matcher::Any)
+        .with_status(202)
+        .with_body(r#"{"reservations": [], "metadata": {"total_count": 0, "page_count": 1}}"#)
+        .create();
+    let live = AirbnbSource::with_credentials(&server.url(), "test-api-key", "test-aat")
+        .unwrap()
+        .with_retry(RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+        });
+    let mut recording = RecordingSource::new(Box::new(live), &dir).unwrap();
+    fetch_reservations(&mut recording, &ReservationQuery::new("2024-01-01")).unwrap();
+
+    let page = &RunManifest::read(&dir).unwrap().pages[0];
+    assert_eq!(page.status, Some(202));
+    assert_eq!(
+        page.attempts,
+        [
+            Attempt {
+                status: Some(429),
+                error: None
+            },
+            Attempt {
+                status: Some(202),
+                error: None
+            },
+        ]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
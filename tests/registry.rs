@@ -0,0 +1,85 @@
+//! Matching of the reservations to the listing registry.
+pub mod common;
+
+use airbnb_reservations_scraper::helpers::engine::get_dataframe;
+use airbnb_reservations_scraper::helpers::mapping::{validate, IssueKind};
+use airbnb_reservations_scraper::helpers::registry::{normalize, ListingRegistry, MatchKind};
+use common::reservation;
+use polars::prelude::*;
+
+fn registry() -> (DataFrame, ListingRegistry) {
+    let listings = df!(
+        "ID_ANUNCIO" => ["", "987654321", ""],
+        "ANUNCIO" => ["Depa Miraflores", "Casa Barranco", "Loft Surco"],
+        "ALIAS" => ["", "", "Loft en Surco | Loft Surco 2"],
+        "PROPIETARIO" => ["Lucia", "Lucia", "Jorge"],
+    )
+    .unwrap();
+    let registry = ListingRegistry::from_dataframe(&listings, 0.8).unwrap();
+    (listings, registry)
+}
+
+#[test]
+fn matches_by_id_then_name_alias_and_similar_name() {
+    let (_, registry) = registry();
+    let kind = |id: &str, name: &str| registry.find(id, name).map(|found| (found.row, found.kind));
+
+    assert_eq!(
+        kind("987654321", "Casa Barranco (renovada)"),
+        Some((1, MatchKind::Id))
+    );
+    assert_eq!(kind("", "Depa Miraflores"), Some((0, MatchKind::Name)));
+    assert_eq!(kind("", "Loft en Surco"), Some((2, MatchKind::Alias)));
+    assert_eq!(
+        kind("123", "  DEPA miraflores! "),
+        Some((0, MatchKind::Normalized))
+    );
+    // Barranco has another listing ID
+    assert_eq!(kind("123", "Casa Barranco"), None);
+    assert_eq!(kind("", "Depa Mirafloes"), Some((0, MatchKind::Fuzzy)));
+    assert!(registry.find("", "Depa Mirafloes").unwrap().score < 1.0);
+    assert_eq!(kind("", "Casa Chorrillos"), None);
+
+    assert_eq!(
+        normalize("Dépa  Miraflores (piso 3)"),
+        "depa miraflores piso 3"
+    );
+}
+
+#[test]
+fn reports_approximate_matches_and_duplicate_ids() {
+    let mut typo = reservation("HMDEF67890", "Confirmed");
+    typo.listing_name = "Depa Mirafloes".to_string();
+    let mut barranco = reservation("HMGHI24680", "Confirmed");
+    barranco.listing_id = "987654321".to_string();
+    barranco.listing_name = "Casa en Barranco".to_string();
+    let reservations = get_dataframe(vec![typo, barranco]).unwrap();
+    let listings = df!(
+        "ID_ANUNCIO" => ["", "987654321", "987654321"],
+        "ANUNCIO" => ["Depa Miraflores", "Casa Barranco", "Loft Surco"],
+        "PROPIETARIO" => ["Lucia", "Lucia", "Lucia"],
+    )
+    .unwrap();
+    let owners = df!("PROPIETARIO" => ["Lucia"]).unwrap();
+    let registry = ListingRegistry::from_dataframe(&listings, 0.8).unwrap();
+
+    let issues = validate(&reservations, &registry, &listings, &owners).unwrap();
+
+    let found: Vec<(IssueKind, &str)> = issues
+        .iter()
+        .map(|issue| (issue.kind, issue.key.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (IssueKind::ApproximateMatch, "Depa Mirafloes"),
+            (IssueKind::DuplicateListing, "987654321"),
+            (IssueKind::UnusedListing, "Loft Surco"),
+        ]
+    );
+    assert!(
+        issues[0].detail.contains("Depa Miraflores (line 2"),
+        "{}",
+        issues[0].detail
+    );
+}
@@ -0,0 +1,103 @@
+//! File and environment secrets backends.
+use airbnb_reservations_scraper::secrets::env::EnvSecrets;
+use airbnb_reservations_scraper::secrets::file::FileSecrets;
+use airbnb_reservations_scraper::secrets::{SecretsProvider, AAT, AIRBNB_API_KEY};
+use airbnb_reservations_scraper::Error;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn secrets_file(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("secrets-{}-{}", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn reads_a_toml_file() {
+    let path = secrets_file(
+        "laptop.toml",
+        "ENABLE = true\n_AAT = \"token\"\nAIRBNB_API_KEY = \"key\"\nRETRIES = 3\n",
+    );
+    let secrets = FileSecrets::new(path.to_str().unwrap()).unwrap();
+
+    assert_eq!(secrets.get(AAT).unwrap(), "token");
+    assert_eq!(secrets.get(AIRBNB_API_KEY).unwrap(), "key");
+    // Non-string values are kept in their text form
+    assert_eq!(secrets.get("ENABLE").unwrap(), "true");
+    assert_eq!(secrets.get("RETRIES").unwrap(), "3");
+}
+
+#[test]
+fn reads_a_json_file() {
+    let path = secrets_file(
+        "ci.json",
+        r#"{"_AAT": "token", "ENABLE": false, "RATE": 1.5}"#,
+    );
+    let secrets = FileSecrets::new(path.to_str().unwrap()).unwrap();
+
+    assert_eq!(secrets.get(AAT).unwrap(), "token");
+    assert_eq!(secrets.get("ENABLE").unwrap(), "false");
+    assert_eq!(secrets.get("RATE").unwrap(), "1.5");
+}
+
+#[test]
+fn reports_missing_keys_and_invalid_files() {
+    let path = secrets_file("partial.toml", "_AAT = \"token\"\n");
+    let secrets = FileSecrets::new(path.to_str().unwrap()).unwrap();
+    match secrets.get(AIRBNB_API_KEY) {
+        Err(Error::Secrets { key, message }) => {
+            assert_eq!(key, AIRBNB_API_KEY);
+            assert!(message.contains("partial.toml"));
+        }
+        other => panic!("expected a secrets error, got {:?}", other.map(|_| ())),
+    }
+
+    let path = secrets_file("broken.json", "{\"_AAT\": ");
+    assert!(matches!(
+        FileSecrets::new(path.to_str().unwrap()),
+        Err(Error::Secrets { .. })
+    ));
+    assert!(FileSecrets::new("/nonexistent/secrets.toml").is_err());
+}
+
+#[test]
+fn reload_picks_up_rotated_values() {
+    let path = secrets_file("rotated.toml", "_AAT = \"old\"\n");
+    let mut secrets = FileSecrets::new(path.to_str().unwrap()).unwrap();
+    assert_eq!(secrets.get(AAT).unwrap(), "old");
+
+    fs::write(&path, "_AAT = \"new\"\nAIRBNB_API_KEY = \"key\"\n").unwrap();
+    assert_eq!(secrets.get(AAT).unwrap(), "old");
+    secrets.reload().unwrap();
+    assert_eq!(secrets.get(AAT).unwrap(), "new");
+    assert_eq!(secrets.get(AIRBNB_API_KEY).unwrap(), "key");
+}
+
+#[test]
+fn env_names_drop_leading_underscores() {
+    assert_eq!(EnvSecrets::var_name(AAT), "SECRET_AAT");
+    assert_eq!(
+        EnvSecrets::var_name(AIRBNB_API_KEY),
+        "SECRET_AIRBNB_API_KEY"
+    );
+    assert_eq!(
+        EnvSecrets::var_name("__TEST_SESSION"),
+        "SECRET_TEST_SESSION"
+    );
+}
+
+#[test]
+fn env_reads_the_secret_variable() {
+    // Keys of their own, the test binary shares its environment across threads
+    env::set_var("SECRET_TEST_ENV_TOKEN", "token");
+    assert_eq!(EnvSecrets.get("_TEST_ENV_TOKEN").unwrap(), "token");
+
+    env::remove_var("SECRET_TEST_ENV_MISSING");
+    match EnvSecrets.get("TEST_ENV_MISSING") {
+        Err(Error::Secrets { message, .. }) => {
+            assert!(message.contains("SECRET_TEST_ENV_MISSING"))
+        }
+        other => panic!("expected a secrets error, got {:?}", other),
+    }
+}
@@ -0,0 +1,107 @@
+//! Owner statements of the consolidated reservations.
+pub mod common;
+
+use airbnb_reservations_scraper::helpers::commission::CommissionDate;
+use airbnb_reservations_scraper::helpers::statement::statements;
+use common::{date, floats, reservation, stay};
+use polars::prelude::*;
+
+/// Lucia's Depa Miraflores in March and April, Jorge's Loft Surco and Cuarto
+/// Lince, with no owner.
+fn consolidated() -> DataFrame {
+    let (start, end) = ("2024-03-01", "2024-03-04");
+    common::consolidated(
+        vec![
+            reservation("HMABC12345", "Confirmed"),
+            stay(
+                "HMDEF67890",
+                "Depa Miraflores",
+                "2024-04-10",
+                "2024-04-12",
+                "S/ 500.00",
+            ),
+            stay("HMGHI24680", "Loft Surco", start, end, "S/ 400.00"),
+            stay("HMJKL13579", "Cuarto Lince", start, end, "S/ 1,250.50"),
+        ],
+        &[Some("Lucia"), Some("Lucia"), Some("Jorge"), None],
+        &[Some("Miraflores"), Some("Miraflores"), Some("Surco"), None],
+        &[18758, 7500, 6000, 18758],
+    )
+}
+
+#[test]
+fn writes_one_statement_per_owner() {
+    let statements =
+        statements(&consolidated(), "ZONA", CommissionDate::CheckIn, None, None).unwrap();
+
+    let owners: Vec<&str> = statements
+        .iter()
+        .map(|statement| statement.owner.as_str())
+        .collect();
+    assert_eq!(owners, ["Jorge", "Lucia"]);
+
+    let lucia = &statements[1];
+    assert_eq!(lucia.zones, ["Miraflores"]);
+    assert_eq!(lucia.period, date("2024-03-01").zip(date("2024-04-12")));
+    assert_eq!(lucia.reservations.height(), 2);
+    assert_eq!(floats(&lucia.totals, "gross"), [Some(1750.5)]);
+    assert_eq!(floats(&lucia.totals, "commission"), [Some(262.58)]);
+    assert_eq!(floats(&lucia.totals, "net"), [Some(1487.92)]);
+    assert!(lucia.totals.column("PROPIETARIO").is_err());
+    assert_eq!(lucia.listings.height(), 1);
+}
+
+#[test]
+fn keeps_the_reservations_of_the_given_period() {
+    let period = date("2024-03-01").zip(date("2024-03-31"));
+
+    let statements = statements(
+        &consolidated(),
+        "ZONA",
+        CommissionDate::CheckIn,
+        period,
+        None,
+    )
+    .unwrap();
+
+    assert!(statements
+        .iter()
+        .all(|statement| statement.period == period));
+    // 3 nights over the 31 days of March
+    assert_eq!(
+        floats(&statements[0].totals, "occupancy"),
+        [Some(3.0 / 31.0)]
+    );
+
+    // Lucia's April stay belongs to the next statement
+    let lucia = &statements[1];
+    assert_eq!(lucia.reservations.height(), 1);
+    assert_eq!(floats(&lucia.totals, "reservations"), [Some(1.0)]);
+    assert_eq!(floats(&lucia.totals, "gross"), [Some(1250.5)]);
+    assert_eq!(floats(&lucia.totals, "commission"), [Some(187.58)]);
+    assert_eq!(floats(&lucia.totals, "net"), [Some(1062.92)]);
+    assert_eq!(floats(&lucia.totals, "occupancy"), [Some(3.0 / 31.0)]);
+}
+
+#[test]
+fn picks_the_period_reservations_by_the_commission_date() {
+    // Both of Lucia's stays were booked on February 10
+    let february = date("2024-02-01").zip(date("2024-02-29"));
+    let in_february = statements(
+        &consolidated(),
+        "ZONA",
+        CommissionDate::Booked,
+        february,
+        None,
+    )
+    .unwrap();
+
+    let lucia = &in_february[1];
+    assert_eq!(lucia.owner, "Lucia");
+    assert_eq!(floats(&lucia.totals, "gross"), [Some(1750.5)]);
+
+    let march = date("2024-03-01").zip(date("2024-03-31"));
+    let in_march =
+        statements(&consolidated(), "ZONA", CommissionDate::Booked, march, None).unwrap();
+    assert!(in_march.is_empty());
+}
@@ -0,0 +1,159 @@
+//! Upserts, persistence and incremental date ranges of the local sync store.
+pub mod common;
+
+use airbnb_reservations_scraper::store::{Store, SyncRecord};
+use airbnb_reservations_scraper::Error;
+use chrono::{DateTime, TimeZone, Utc};
+use common::reservation;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn store_path(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("reservations-store-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.jsonl", name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn at(day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap()
+}
+
+fn sync_record(date_min: &str, date_max: Option<&str>, finished_at: DateTime<Utc>) -> SyncRecord {
+    SyncRecord {
+        finished_at,
+        date_min: date_min.to_string(),
+        date_max: date_max.map(str::to_string),
+        fetched_from: date_min.to_string(),
+        new: 0,
+        updated: 0,
+        unchanged: 0,
+    }
+}
+
+#[test]
+fn upserts_new_changed_and_unchanged_reservations() {
+    let mut store = Store::open(&store_path("upsert")).unwrap();
+    let counts = store.upsert(
+        vec![
+            reservation("HMABC12345", "Confirmed"),
+            reservation("HMDEF67890", "Confirmed"),
+        ],
+        at(1),
+    );
+    assert_eq!((counts.new, counts.updated, counts.unchanged), (2, 0, 0));
+
+    let counts = store.upsert(
+        vec![
+            reservation("HMABC12345", "Cancelled"),
+            reservation("HMDEF67890", "Confirmed"),
+        ],
+        at(2),
+    );
+    assert_eq!((counts.new, counts.updated, counts.unchanged), (0, 1, 1));
+
+    let changed = store.get("HMABC12345").unwrap();
+    assert_eq!(changed.reservation.status, "Cancelled");
+    assert_eq!(changed.first_seen, at(1));
+    assert_eq!(changed.last_seen, at(2));
+    assert_eq!(changed.last_changed, at(2));
+
+    let unchanged = store.get("HMDEF67890").unwrap();
+    assert_eq!(unchanged.last_seen, at(2));
+    assert_eq!(unchanged.last_changed, at(1));
+}
+
+#[test]
+fn saves_and_reloads_reservations_and_syncs() {
+    let path = store_path("reload");
+    let mut store = Store::open(&path).unwrap();
+    store.upsert(vec![reservation("HMABC12345", "Confirmed")], at(1));
+    store.record_sync(sync_record("2024-01-01", None, at(1)));
+    store.save().unwrap();
+
+    let reloaded = Store::open(&path).unwrap();
+    assert_eq!(reloaded.len(), 1);
+    assert_eq!(
+        reloaded.get("HMABC12345"),
+        store.get("HMABC12345"),
+        "stored reservation should survive a round trip"
+    );
+    assert_eq!(reloaded.syncs(), store.syncs());
+}
+
+#[test]
+fn fetches_everything_without_a_previous_sync() {
+    let store = Store::open(&store_path("first-sync")).unwrap();
+    assert_eq!(
+        store.sync_date_min("2024-01-01", None, 30).unwrap(),
+        "2024-01-01"
+    );
+}
+
+#[test]
+fn fetches_only_the_lookback_window_after_a_covering_sync() {
+    let mut store = Store::open(&store_path("lookback")).unwrap();
+    store.record_sync(sync_record("2024-01-01", None, at(31)));
+
+    assert_eq!(
+        store.sync_date_min("2024-01-01", None, 30).unwrap(),
+        "2024-03-01"
+    );
+    // The requested date_min still wins when it is later
+    assert_eq!(
+        store.sync_date_min("2024-03-15", None, 30).unwrap(),
+        "2024-03-15"
+    );
+}
+
+#[test]
+fn keeps_the_lookback_window_inside_a_covered_past_range() {
+    let mut store = Store::open(&store_path("past-range")).unwrap();
+    store.record_sync(sync_record("2024-01-01", Some("2024-01-31"), at(31)));
+
+    // Synced again months later, the window starts at date_max at the latest
+    assert_eq!(
+        store
+            .sync_date_min("2024-01-01", Some("2024-01-31"), 30)
+            .unwrap(),
+        "2024-01-31"
+    );
+    assert_eq!(
+        store
+            .sync_date_min("2024-01-01", Some("2024-01-20"), 30)
+            .unwrap(),
+        "2024-01-20"
+    );
+}
+
+#[test]
+fn rejects_a_lookback_out_of_the_range_of_dates() {
+    let mut store = Store::open(&store_path("huge-lookback")).unwrap();
+    store.record_sync(sync_record("2024-01-01", None, at(31)));
+
+    for lookback_days in [i64::MAX, 1_000_000_000, i64::MIN] {
+        assert!(matches!(
+            store.sync_date_min("2024-01-01", None, lookback_days),
+            Err(Error::Config(_))
+        ));
+    }
+}
+
+#[test]
+fn fetches_everything_when_the_range_was_not_covered() {
+    let mut store = Store::open(&store_path("uncovered")).unwrap();
+    store.record_sync(sync_record("2024-02-01", Some("2024-02-29"), at(31)));
+
+    assert_eq!(
+        store
+            .sync_date_min("2024-01-01", Some("2024-02-29"), 30)
+            .unwrap(),
+        "2024-01-01"
+    );
+    assert_eq!(
+        store.sync_date_min("2024-02-01", None, 30).unwrap(),
+        "2024-02-01"
+    );
+}
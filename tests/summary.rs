@@ -0,0 +1,207 @@
+//! Summary sheets of the consolidated reservations.
+pub mod common;
+
+use airbnb_reservations_scraper::helpers::summary::{summarize, ListingCounts, Summary};
+use common::{date, floats, reservation, stay};
+use polars::prelude::*;
+
+/// Two listings of the same owner: two stays in Miraflores (March and April)
+/// and one in Barranco, with their commissions in cents.
+fn consolidated(with_owner: bool) -> DataFrame {
+    let owners = [Some("Lucia"); 3];
+    let zones = [Some("Miraflores"), Some("Miraflores"), Some("Barranco")];
+    let mapped = if with_owner { owners.len() } else { 0 };
+    common::consolidated(
+        vec![
+            reservation("HMABC12345", "Confirmed"),
+            stay(
+                "HMDEF67890",
+                "Depa Miraflores",
+                "2024-04-10",
+                "2024-04-12",
+                "S/ 500.00",
+            ),
+            stay(
+                "HMGHI24680",
+                "Casa Barranco",
+                "2024-03-10",
+                "2024-03-14",
+                "S/ 800.00",
+            ),
+        ],
+        &owners[..mapped],
+        &zones[..mapped],
+        &[18758, 7500, 12000],
+    )
+}
+
+fn sheet<'a>(summaries: &'a [Summary], name: &str) -> &'a DataFrame {
+    &summaries
+        .iter()
+        .find(|summary| summary.sheet == name)
+        .unwrap_or_else(|| panic!("no {} sheet", name))
+        .df
+}
+
+fn ratio(nights: f64, days: f64) -> Option<f64> {
+    Some(nights / days)
+}
+
+#[test]
+fn totals_each_listing_owner_and_zone() {
+    let period = (date("2024-03-01").unwrap(), date("2024-04-30").unwrap());
+    let summaries = summarize(&consolidated(true), "ZONA", Some(period), None).unwrap();
+
+    let sheets: Vec<&str> = summaries
+        .iter()
+        .map(|summary| summary.sheet.as_str())
+        .collect();
+    assert_eq!(sheets, ["By listing", "By owner", "By zone", "By month"]);
+
+    let by_listing = sheet(&summaries, "By listing");
+    assert_eq!(
+        by_listing
+            .column("listing_name")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>(),
+        [Some("Casa Barranco"), Some("Depa Miraflores")]
+    );
+    assert_eq!(floats(by_listing, "reservations"), [Some(1.0), Some(2.0)]);
+    assert_eq!(floats(by_listing, "nights"), [Some(4.0), Some(5.0)]);
+    assert_eq!(floats(by_listing, "gross"), [Some(800.0), Some(1750.5)]);
+    assert_eq!(
+        floats(by_listing, "commission"),
+        [Some(120.0), Some(262.58)]
+    );
+    assert_eq!(floats(by_listing, "net"), [Some(680.0), Some(1487.92)]);
+    // 61 days between March 1st and April 30th
+    assert_eq!(
+        floats(by_listing, "occupancy"),
+        [ratio(4.0, 61.0), ratio(5.0, 61.0)]
+    );
+
+    let by_owner = sheet(&summaries, "By owner");
+    assert_eq!(by_owner.height(), 1);
+    assert_eq!(floats(by_owner, "listings"), [Some(2.0)]);
+    assert_eq!(floats(by_owner, "occupancy"), [ratio(9.0, 122.0)]);
+
+    assert_eq!(sheet(&summaries, "By zone").height(), 2);
+}
+
+#[test]
+fn measures_monthly_occupancy_without_a_period() {
+    let summaries = summarize(&consolidated(false), "ZONA", None, None).unwrap();
+
+    let sheets: Vec<&str> = summaries
+        .iter()
+        .map(|summary| summary.sheet.as_str())
+        .collect();
+    assert_eq!(sheets, ["By listing", "By month"]);
+    assert_eq!(
+        floats(sheet(&summaries, "By listing"), "occupancy"),
+        [None, None]
+    );
+
+    let by_month = sheet(&summaries, "By month");
+    assert_eq!(
+        by_month
+            .column("month")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>(),
+        [Some("2024-03"), Some("2024-04")]
+    );
+    assert_eq!(floats(by_month, "listings"), [Some(2.0), Some(1.0)]);
+    assert_eq!(
+        floats(by_month, "occupancy"),
+        [ratio(7.0, 62.0), ratio(2.0, 30.0)]
+    );
+}
+
+#[test]
+fn counts_only_the_nights_inside_the_period_and_the_month() {
+    let df = common::consolidated(
+        vec![
+            stay(
+                "HMABC12345",
+                "Depa Miraflores",
+                "2024-03-30",
+                "2024-04-05",
+                "S/ 600.00",
+            ),
+            stay(
+                "HMDEF67890",
+                "Depa Miraflores",
+                "2024-04-10",
+                "2024-04-12",
+                "S/ 500.00",
+            ),
+        ],
+        &[],
+        &[],
+        &[9000, 7500],
+    );
+    let march = (date("2024-03-01").unwrap(), date("2024-03-31").unwrap());
+    let summaries = summarize(&df, "ZONA", Some(march), None).unwrap();
+
+    let by_listing = sheet(&summaries, "By listing");
+    assert_eq!(floats(by_listing, "nights"), [Some(8.0)]);
+    // Only the nights of March 30 and 31 are in the period
+    assert_eq!(floats(by_listing, "occupancy"), [ratio(2.0, 31.0)]);
+
+    // The first stay adds 2 nights to March and 4 to April
+    let by_month = sheet(&summaries, "By month");
+    assert_eq!(
+        floats(by_month, "occupancy"),
+        [ratio(2.0, 31.0), ratio(6.0, 30.0)]
+    );
+}
+
+#[test]
+fn measures_owners_zones_and_months_against_the_registry() {
+    // The last row has no listing name and is left out
+    let registry = df!(
+        "ANUNCIO" => ["Depa Miraflores", "Casa Barranco", "Depa Larco", "Loft Barranco", "Casa Chorrillos", ""],
+        "PROPIETARIO" => ["Lucia", "Lucia", "Lucia", "Lucia", "Jorge", "Jorge"],
+        "ZONA" => ["Miraflores", "Barranco", "Miraflores", "Barranco", "Chorrillos", ""],
+    )
+    .unwrap();
+    let counts = ListingCounts::from_registry(&registry, "ZONA").unwrap();
+    assert_eq!(counts.total, 5);
+    assert_eq!(counts.by_owner["Lucia"], 4);
+    assert_eq!(counts.by_owner["Jorge"], 1);
+    assert_eq!(counts.by_zone["Barranco"], 2);
+
+    let period = (date("2024-03-01").unwrap(), date("2024-04-30").unwrap());
+    let summaries = summarize(&consolidated(true), "ZONA", Some(period), Some(&counts)).unwrap();
+
+    // 9 booked nights over 4 listings for 61 days, not over the 2 booked ones
+    let by_owner = sheet(&summaries, "By owner");
+    assert_eq!(floats(by_owner, "listings"), [Some(4.0)]);
+    assert_eq!(floats(by_owner, "occupancy"), [ratio(9.0, 244.0)]);
+
+    let by_zone = sheet(&summaries, "By zone");
+    assert_eq!(floats(by_zone, "listings"), [Some(2.0), Some(2.0)]);
+    assert_eq!(
+        floats(by_zone, "occupancy"),
+        [ratio(4.0, 122.0), ratio(5.0, 122.0)]
+    );
+
+    // Listings still count one each
+    assert_eq!(
+        floats(sheet(&summaries, "By listing"), "listings"),
+        [Some(1.0), Some(1.0)]
+    );
+
+    let by_month = sheet(&summaries, "By month");
+    assert_eq!(floats(by_month, "listings"), [Some(5.0), Some(5.0)]);
+    assert_eq!(
+        floats(by_month, "occupancy"),
+        [ratio(7.0, 155.0), ratio(2.0, 150.0)]
+    );
+}